@@ -166,9 +166,16 @@ pub fn impl_system_data(input: TokenStream) -> TokenStream{
     }
     out_stream.concat(format!(")}}"));
 
+    // A tuple uses everything any of its members use, intersecting them would hide conflicts from the scheduler
     out_stream.concat(format!("fn get_dep_vec<'w: 'd, W: WorldCommon>(world: &W) -> DepVec{{ {}::get_dep_vec(world)", tokens[0]));
     for token in tokens.iter().skip(1){
-        out_stream.concat(format!(".and(&{}::get_dep_vec(world))", token));
+        out_stream.concat(format!(".or(&{}::get_dep_vec(world))", token));
+    }
+    out_stream.concat(format!("}}"));
+
+    out_stream.concat(format!("fn setup<W: WorldCommon>(world: &mut W){{"));
+    for token in tokens.iter(){
+        out_stream.concat(format!("{}::setup(world);", token));
     }
     out_stream.concat(format!("}} }}"));
     
@@ -196,4 +203,65 @@ pub fn impl_system_data_multi(input: TokenStream) -> TokenStream {
     }
 
     out_stream.parse().unwrap()
-}
\ No newline at end of file
+}
+
+#[proc_macro]
+pub fn impl_function_system(input: TokenStream) -> TokenStream{
+    let tokens: Vec<String> = input
+        .to_string()
+        .split(',')
+        .map(|token|{
+            token.trim().to_uppercase()
+        })
+        .collect();
+
+    let mut generics = String::from("'d, 'w: 'd, W: WorldCommon, Func");
+    for token in tokens.iter(){
+        generics.concat(format!(", {}: SystemData<'d>", token));
+    }
+
+    let params = tokens.join(", ");
+    let args = tokens.iter().map(|token| token.to_lowercase()).collect::<Vec<String>>().join(", ");
+
+    // A single parameter is its own SystemData, more than one is a tuple of them
+    let (data, pattern) = if tokens.len() == 1{
+        (params.clone(), args.clone())
+    }
+    else{
+        (format!("({})", params), format!("({})", args))
+    };
+
+    let mut out_stream = String::new();
+
+    out_stream.concat(format!("impl<{}> System<'d, 'w, W> for FunctionSystem<Func, ({},)> where Func: Fn({}){{", generics, params, params));
+    out_stream.concat(format!("type SystemData = {};", data));
+    out_stream.concat(format!("fn run(&self, {}: Self::SystemData){{ (self.func)({}) }} }}", pattern, args));
+
+    out_stream.concat(format!("impl<{}> IntoSystem<'d, 'w, W, fn({})> for Func where Func: Fn({}){{", generics, params, params));
+    out_stream.concat(format!("type System = FunctionSystem<Func, ({},)>;", params));
+    out_stream.concat(format!("fn into_system(self) -> Self::System{{ FunctionSystem{{ func: self, marker: PhantomData }} }} }}"));
+
+    out_stream.parse().unwrap()
+}
+
+#[proc_macro]
+pub fn impl_function_system_multi(input: TokenStream) -> TokenStream {
+    let arg = input.to_string().parse::<usize>().unwrap();
+
+    let mut out_stream = String::new();
+
+    for i in 0..arg{
+        out_stream.concat(format!("impl_function_system!("));
+        for j in 0..=i{
+            if j == 0{
+                out_stream.concat(format!("T{}", j));
+            }
+            else{
+                out_stream.concat(format!(", T{}", j));
+            }
+        }
+        out_stream.concat(format!(");"));
+    }
+
+    out_stream.parse().unwrap()
+}
@@ -2,6 +2,8 @@ pub mod entity;
 pub mod component;
 pub mod system;
 pub mod join;
+pub mod time;
+pub mod profiler;
 use std::any::Any;
 
 use std::cell::{RefCell, Ref, RefMut};
@@ -19,6 +21,8 @@ pub trait WorldCommon{
     fn get_mut<T: Any>(&self) -> MappedRwLockWriteGuard<T>;
 
     fn insert<R: 'static + Any>(&mut self, resource: R);
+
+    fn contains<R: Any>(&self) -> bool;
     
     fn get_comp<T: Component + Any>(&self) -> MappedRwLockReadGuard<ComponentStorage<T>>;
 
@@ -26,6 +30,8 @@ pub trait WorldCommon{
 
     fn register_comp<T: 'static + Component + Any>(&mut self);
 
+    fn contains_comp<T: Any>(&self) -> bool;
+
     fn get_dep_vec_res<T: Any>(&self, at: AccessType) -> DepVec;
 
     fn get_dep_vec_comp<T: Any>(&self, at: AccessType) -> DepVec;
@@ -87,61 +93,55 @@ impl DepVec{
         check
     }
 
-    fn len_fix_single(&mut self, other: &mut BitVec){
-        if self.res_read.len() < other.len(){
-            self.res_read.grow(other.len() - self.res_read.len(), false);
-        }
-        if self.res_read.len() > other.len(){
-            other.grow(self.res_read.len() - other.len(), false);
-        }
-        
-        if self.res_write.len() < other.len(){
-            self.res_write.grow(other.len() - self.res_write.len(), false);
-        }
-        if self.res_write.len() > other.len(){
-            other.grow(self.res_write.len() - other.len(), false);
-        }
-        
-        if self.comp_read.len() < other.len(){
-            self.comp_read.grow(other.len() - self.comp_read.len(), false);
-        }
-        if self.comp_read.len() > other.len(){
-            other.grow(self.comp_read.len() - other.len(), false);
-        }
-        
-        if self.comp_write.len() < other.len(){
-            self.comp_write.grow(other.len() - self.comp_write.len(), false);
-        }
-        if self.comp_write.len() > other.len(){
-            other.grow(self.comp_write.len() - other.len(), false);
-        }
-    }
-
-    pub fn intersection(&self, mut other_res: BitVec, mut other_comp: BitVec) -> bool{
+    /// Combines the accesses of two DepVecs, used to build the DepVec of a tuple of SystemData
+    pub fn or(&self, other: &DepVec) -> DepVec{
         let mut check = self.clone();
-        check.len_fix_single(&mut other_res);
+        let mut other = other.clone();
+        check.len_fix(&mut other);
+
+        check.res_read.or(&other.res_read);
 
-        let mut res_read = check.res_read.clone();
-        res_read.and(&other_res);
-        let mut res_write = check.res_write.clone();
-        res_write.and(&other_res);
+        check.res_write.or(&other.res_write);
         
-        if res_read.any() || res_write.any(){
-            return true;
-        }
+        check.comp_read.or(&other.comp_read);
+        
+        check.comp_write.or(&other.comp_write);
 
-        check.len_fix_single(&mut other_comp);
+        check
+    }
 
-        let mut comp_read = check.comp_read.clone();
-        comp_read.and(&other_comp);
-        let mut comp_write = check.comp_write.clone();
-        comp_write.and(&other_comp);
+    /// Resources and components that one side writes and the other reads or writes, as (resources, components)
+    pub fn conflicts(&self, other: &DepVec) -> (BitVec, BitVec){
+        fn conflict(a_read: &BitVec, a_write: &BitVec, b_read: &BitVec, b_write: &BitVec) -> BitVec{
+            let len = [a_read.len(), a_write.len(), b_read.len(), b_write.len()].iter().copied().max().unwrap();
+            let grown = |bits: &BitVec|{
+                let mut bits = bits.clone();
+                bits.grow(len - bits.len(), false);
+                bits
+            };
+
+            let mut b_any = grown(b_read);
+            b_any.or(&grown(b_write));
+            let mut out = grown(a_write);
+            out.and(&b_any);
+
+            let mut other_way = grown(b_write);
+            other_way.and(&grown(a_read));
+            out.or(&other_way);
+            out
+        }
+
+        (conflict(&self.res_read, &self.res_write, &other.res_read, &other.res_write),
+            conflict(&self.comp_read, &self.comp_write, &other.comp_read, &other.comp_write))
+    }
 
-        if comp_read.any() || comp_write.any(){
-            return true;
-        }
+    /// Whether anything this DepVec reads or writes is in the given resources or components.
+    /// Bits past the end of the shorter side count as unset, resource and component bitsets can differ in length.
+    pub fn intersection(&self, other_res: BitVec, other_comp: BitVec) -> bool{
+        let overlaps = |bits: &BitVec, other: &BitVec| bits.iter().zip(other.iter()).any(|(a, b)| a && b);
 
-        false
+        overlaps(&self.res_read, &other_res) || overlaps(&self.res_write, &other_res)
+            || overlaps(&self.comp_read, &other_comp) || overlaps(&self.comp_write, &other_comp)
     }
 }
 
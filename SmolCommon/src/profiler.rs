@@ -0,0 +1,199 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use std::fmt::Write;
+
+/// One run of one system, times are relative to when the profiler was created
+#[derive(Clone, Debug)]
+pub struct SystemSpan{
+    pub system: String,
+    /// Index of the worker thread in the scheduler's pool, None if it ran outside the pool
+    pub thread: Option<usize>,
+    pub start: Duration,
+    /// When the system got all of its locks and started running
+    pub acquired: Duration,
+    pub end: Duration,
+}
+
+impl SystemSpan{
+    pub fn lock_wait(&self) -> Duration{
+        self.acquired - self.start
+    }
+
+    pub fn run_time(&self) -> Duration{
+        self.end - self.acquired
+    }
+}
+
+/// Rolling stats over the last few runs of a system
+#[derive(Clone, Debug)]
+pub struct SystemStats{
+    window: usize,
+    runs: u64,
+    run_times: VecDeque<Duration>,
+    lock_waits: VecDeque<Duration>,
+}
+
+impl SystemStats{
+    fn new(window: usize) -> Self{
+        SystemStats{
+            window,
+            runs: 0,
+            run_times: VecDeque::with_capacity(window),
+            lock_waits: VecDeque::with_capacity(window),
+        }
+    }
+
+    fn push(&mut self, span: &SystemSpan){
+        if self.run_times.len() == self.window{
+            self.run_times.pop_front();
+            self.lock_waits.pop_front();
+        }
+        self.run_times.push_back(span.run_time());
+        self.lock_waits.push_back(span.lock_wait());
+        self.runs += 1;
+    }
+
+    /// Total number of runs recorded, including ones that have left the window
+    pub fn runs(&self) -> u64{
+        self.runs
+    }
+
+    pub fn last(&self) -> Duration{
+        self.run_times.back().copied().unwrap_or_default()
+    }
+
+    pub fn mean(&self) -> Duration{
+        mean(&self.run_times)
+    }
+
+    pub fn min(&self) -> Duration{
+        self.run_times.iter().min().copied().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> Duration{
+        self.run_times.iter().max().copied().unwrap_or_default()
+    }
+
+    pub fn mean_lock_wait(&self) -> Duration{
+        mean(&self.lock_waits)
+    }
+}
+
+fn mean(times: &VecDeque<Duration>) -> Duration{
+    if times.is_empty(){
+        return Duration::default();
+    }
+    times.iter().sum::<Duration>() / times.len() as u32
+}
+
+/// Records when and where systems ran.
+/// Insert this as a resource and enable profiling on a scheduler to fill it in.
+pub struct Profiler{
+    epoch: Instant,
+    window: usize,
+    max_spans: usize,
+    spans: VecDeque<SystemSpan>,
+    stats: HashMap<String, SystemStats>,
+}
+
+impl Default for Profiler{
+    fn default() -> Self{
+        Profiler::new(120, 4096)
+    }
+}
+
+impl Profiler{
+    /// Stats are kept over the last `window` runs of each system, only the last `max_spans` spans are kept for tracing
+    pub fn new(window: usize, max_spans: usize) -> Self{
+        Profiler{
+            epoch: Instant::now(),
+            window: window.max(1),
+            max_spans,
+            spans: VecDeque::new(),
+            stats: HashMap::new(),
+        }
+    }
+
+    /// Converts an instant to the profiler's time base
+    pub fn since_epoch(&self, instant: Instant) -> Duration{
+        instant.saturating_duration_since(self.epoch)
+    }
+
+    pub fn record(&mut self, span: SystemSpan){
+        let window = self.window;
+        self.stats.entry(span.system.clone())
+            .or_insert_with(|| SystemStats::new(window))
+            .push(&span);
+
+        if self.spans.len() == self.max_spans{
+            self.spans.pop_front();
+        }
+        if self.max_spans > 0{
+            self.spans.push_back(span);
+        }
+    }
+
+    pub fn stats(&self, system: &str) -> Option<&SystemStats>{
+        self.stats.get(system)
+    }
+
+    pub fn all_stats(&self) -> impl Iterator<Item = (&String, &SystemStats)>{
+        self.stats.iter()
+    }
+
+    pub fn spans(&self) -> impl Iterator<Item = &SystemSpan>{
+        self.spans.iter()
+    }
+
+    /// Drops the recorded spans but keeps the rolling stats, call this at the start of a frame to trace just that frame
+    pub fn clear_spans(&mut self){
+        self.spans.clear();
+    }
+
+    /// Time spent running systems divided by wall time covered by the recorded spans.
+    /// 1.0 means nothing ran in parallel.
+    pub fn parallelism(&self) -> f64{
+        let start = self.spans.iter().map(|s| s.start).min();
+        let end = self.spans.iter().map(|s| s.end).max();
+        match (start, end){
+            (Some(start), Some(end)) if end > start => {
+                let busy: Duration = self.spans.iter().map(|s| s.end - s.start).sum();
+                busy.as_secs_f64() / (end - start).as_secs_f64()
+            },
+            _ => 0.0,
+        }
+    }
+
+    /// Exports the recorded spans in the Chrome trace_event format, load it in chrome://tracing or Perfetto
+    pub fn chrome_trace(&self) -> String{
+        let mut out = String::from("{\"traceEvents\":[");
+        for (n, span) in self.spans.iter().enumerate(){
+            if n != 0{
+                out.push(',');
+            }
+            write!(out,
+                "{{\"name\":\"{}\",\"cat\":\"system\",\"ph\":\"X\",\"pid\":1,\"tid\":{},\"ts\":{},\"dur\":{},\"args\":{{\"lock_wait_us\":{}}}}}",
+                escape_json(&span.system),
+                span.thread.map(|t| t + 1).unwrap_or(0),
+                span.start.as_micros(),
+                (span.end - span.start).as_micros(),
+                span.lock_wait().as_micros()).unwrap();
+        }
+        out.push_str("],\"displayTimeUnit\":\"ms\"}");
+        out
+    }
+}
+
+fn escape_json(s: &str) -> String{
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars(){
+        match c{
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out
+}
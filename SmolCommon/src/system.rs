@@ -6,16 +6,23 @@ use super::{WorldCommon, Resource, DepVec, AccessType};
 use std::ops::{Deref, DerefMut};
 use std::marker::PhantomData;
 use std::cell::{RefCell, Ref, RefMut};
+use std::time::Instant;
+use std::any::Any;
 
 use bit_vec::BitVec;
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard, MappedRwLockReadGuard, MappedRwLockWriteGuard};
 
-use SmolCommonMacros::{impl_system_data, impl_system_data_multi};
+use SmolCommonMacros::{impl_system_data, impl_system_data_multi, impl_function_system, impl_function_system_multi};
 
 pub trait Scheduler<'d, 'w: 'd, W: WorldCommon>{
-    fn add<S:'w + System<'d, 'w, W>>(&mut self, system: S, name: &str, depend: Vec<&str>);
+    /// Adds a system, or a function or closure whose parameters are all SystemData
+    fn add<M, S: IntoSystem<'d, 'w, W, M>>(&mut self, system: S, name: &str, depend: Vec<&str>)
+        where S::System: 'w;
 
     fn run(&self, world: &'w W);
+
+    /// Sets up the data of every system added so far
+    fn setup(&self, world: &mut W);
 }
 
 pub trait System<'d, 'w: 'd, W: WorldCommon>{
@@ -31,11 +38,22 @@ pub trait System<'d, 'w: 'd, W: WorldCommon>{
     fn get_system_dependencies(&self, world: &W) -> DepVec{
         Self::SystemData::get_dep_vec(world)
     }
+
+    /// Registers the storages and inserts the resources this system needs
+    fn setup(&self, world: &mut W){
+        Self::SystemData::setup(world)
+    }
 }
 
 pub trait SystemRunner<'d, 'w: 'd, W: WorldCommon>{
     fn get_and_run(&self, world: &'w W);
+
+    /// Runs the system, returning when it got its data and when it finished
+    fn get_and_run_timed(&self, world: &'w W) -> (Instant, Instant);
+
     fn get_system_dependencies(&self, world: &W) -> DepVec;
+
+    fn setup(&self, world: &mut W);
 }
 
 impl<'d, 'w: 'd, W: WorldCommon, T, Q> SystemRunner<'d, 'w, W> for T
@@ -46,14 +64,55 @@ impl<'d, 'w: 'd, W: WorldCommon, T, Q> SystemRunner<'d, 'w, W> for T
         self.run(T::SystemData::get_data(world));
     }
 
+    fn get_and_run_timed(&self, world: &'w W) -> (Instant, Instant){
+        let data = T::SystemData::get_data(world);
+        let acquired = Instant::now();
+        self.run(data);
+        (acquired, Instant::now())
+    }
+
     fn get_system_dependencies(&self, world: &W) -> DepVec {
         T::SystemData::get_dep_vec(world)
     }
+
+    fn setup(&self, world: &mut W){
+        System::setup(self, world)
+    }
+}
+
+/// Anything that can be turned into a system, `M` only exists to keep the implementations apart
+pub trait IntoSystem<'d, 'w: 'd, W: WorldCommon, M>{
+    type System: System<'d, 'w, W>;
+
+    fn into_system(self) -> Self::System;
 }
 
+/// Marks the IntoSystem implementation for types that already are systems
+pub struct IsSystem;
+
+impl<'d, 'w: 'd, W: WorldCommon, S> IntoSystem<'d, 'w, W, IsSystem> for S
+    where S: System<'d, 'w, W>{
+    type System = S;
+
+    fn into_system(self) -> Self::System{
+        self
+    }
+}
+
+/// A system made from a function or closure, its SystemData is the tuple of its parameters
+pub struct FunctionSystem<F, P>{
+    func: F,
+    marker: PhantomData<fn(P)>,
+}
+
+impl_function_system_multi!{16}
+
 pub trait SystemData<'d>{
     fn get_data<'w: 'd, W: WorldCommon>(world: &'w W) -> Self;
     fn get_dep_vec<'w: 'd, W: WorldCommon>(world: &W) -> DepVec;
+
+    /// Makes sure the storages and resources this data needs exist in the world
+    fn setup<W: WorldCommon>(_world: &mut W){}
 }
 
 pub struct ReadComp<'d, T: 'static + Component>{
@@ -77,6 +136,12 @@ impl<'d, T> SystemData<'d> for ReadComp<'d, T>
     fn get_dep_vec<'w: 'd, W: WorldCommon>(world: &W) -> DepVec{
         world.get_dep_vec_comp::<T>(AccessType::Read)
     }
+
+    fn setup<W: WorldCommon>(world: &mut W){
+        if !world.contains_comp::<T>(){
+            world.register_comp::<T>();
+        }
+    }
 }
 
 impl<'j, 'd, T> Joinable<'j> for &'j ReadComp<'d, T>
@@ -123,6 +188,12 @@ impl<'d, T> SystemData<'d> for WriteComp<'d, T>
     fn get_dep_vec<'w: 'd, W: WorldCommon>(world: &W) -> DepVec{
         world.get_dep_vec_comp::<T>(AccessType::Write)
     }
+
+    fn setup<W: WorldCommon>(world: &mut W){
+        if !world.contains_comp::<T>(){
+            world.register_comp::<T>();
+        }
+    }
 }
 
 impl<'j, 'd: 'j, T> Joinable<'j> for &'j mut WriteComp<'d, T>
@@ -147,11 +218,51 @@ impl<'j, 'd: 'j, T> Joinable<'j> for &'j WriteComp<'d, T>
     }
 }
 
-pub struct Read<'d, T: 'static + Resource>{
-    comp: MappedRwLockReadGuard<'d, T>
+/// Decides what happens to a missing resource when a system using it is set up
+pub trait SetupHandler<T>{
+    fn setup<W: WorldCommon>(world: &mut W);
 }
 
-impl<'d, T: Resource> Deref for Read<'d, T>{
+/// Leaves the resource alone, for resources that only get inserted after the systems are set up
+pub struct NoSetup;
+
+impl<T> SetupHandler<T> for NoSetup{
+    fn setup<W: WorldCommon>(_world: &mut W){}
+}
+
+/// Inserts the resource's default value if it's missing
+pub struct DefaultProvider;
+
+impl<T: Any + Default> SetupHandler<T> for DefaultProvider{
+    fn setup<W: WorldCommon>(world: &mut W){
+        if !world.contains::<T>(){
+            world.insert(T::default());
+        }
+    }
+}
+
+/// Panics if the resource is missing, for resources that have no sensible default. What plain `Read` and `Write` do.
+pub struct PanicHandler;
+
+impl<T: Any> SetupHandler<T> for PanicHandler{
+    fn setup<W: WorldCommon>(world: &mut W){
+        assert!(world.contains::<T>(), "Resource {} has to be inserted before setting up systems that use it", std::any::type_name::<T>());
+    }
+}
+
+/// Reads a resource. Setup only inserts it for `ReadDefault`, plain `Read` panics naming the resource if it's missing.
+pub struct Read<'d, T: 'static + Resource, F = PanicHandler>{
+    comp: MappedRwLockReadGuard<'d, T>,
+    marker: PhantomData<F>,
+}
+
+/// Reads a resource that has to be inserted by hand, setup panics if it's missing. The same as plain `Read`.
+pub type ReadExpect<'d, T> = Read<'d, T, PanicHandler>;
+
+/// Reads a resource, setup inserts its default value if it's missing
+pub type ReadDefault<'d, T> = Read<'d, T, DefaultProvider>;
+
+impl<'d, T: Resource, F> Deref for Read<'d, T, F>{
     type Target = T;
 
     fn deref(&self) -> &Self::Target{
@@ -159,24 +270,38 @@ impl<'d, T: Resource> Deref for Read<'d, T>{
     }
 }
 
-impl<'d, T> SystemData<'d> for Read<'d, T>
-    where T: Resource + 'static{
+impl<'d, T, F> SystemData<'d> for Read<'d, T, F>
+    where T: Resource + 'static,
+          F: SetupHandler<T>{
     fn get_data<'w: 'd, W: WorldCommon>(world: &'w W) -> Self{
         Self{
-            comp: world.get::<T>()
+            comp: world.get::<T>(),
+            marker: PhantomData,
         }
     }
 
     fn get_dep_vec<'w: 'd, W: WorldCommon>(world: &W) -> DepVec{
         world.get_dep_vec_res::<T>(AccessType::Read)
     }
+
+    fn setup<W: WorldCommon>(world: &mut W){
+        F::setup(world);
+    }
 }
 
-pub struct Write<'d, T: 'static + Resource>{
-    comp: MappedRwLockWriteGuard<'d, T>
+/// Writes a resource. Setup only inserts it for `WriteDefault`, plain `Write` panics naming the resource if it's missing.
+pub struct Write<'d, T: 'static + Resource, F = PanicHandler>{
+    comp: MappedRwLockWriteGuard<'d, T>,
+    marker: PhantomData<F>,
 }
 
-impl<'d, T: Resource> Deref for Write<'d, T>{
+/// Writes a resource that has to be inserted by hand, setup panics if it's missing. The same as plain `Write`.
+pub type WriteExpect<'d, T> = Write<'d, T, PanicHandler>;
+
+/// Writes a resource, setup inserts its default value if it's missing
+pub type WriteDefault<'d, T> = Write<'d, T, DefaultProvider>;
+
+impl<'d, T: Resource, F> Deref for Write<'d, T, F>{
     type Target = T;
 
     fn deref(&self) -> &Self::Target{
@@ -184,23 +309,29 @@ impl<'d, T: Resource> Deref for Write<'d, T>{
     }
 }
 
-impl<'d, T: Resource> DerefMut for Write<'d, T>{
+impl<'d, T: Resource, F> DerefMut for Write<'d, T, F>{
     fn deref_mut(&mut self) -> &mut Self::Target{
         self.comp.deref_mut()
     }
 }
 
-impl<'d, T> SystemData<'d> for Write<'d, T>
-    where T: Resource + 'static{
+impl<'d, T, F> SystemData<'d> for Write<'d, T, F>
+    where T: Resource + 'static,
+          F: SetupHandler<T>{
     fn get_data<'w: 'd, W: WorldCommon>(world: &'w W) -> Self{
         Self{
-            comp: world.get_mut::<T>()
+            comp: world.get_mut::<T>(),
+            marker: PhantomData,
         }
     }
 
     fn get_dep_vec<'w: 'd, W: WorldCommon>(world: &W) -> DepVec{
         world.get_dep_vec_res::<T>(AccessType::Write)
     }
+
+    fn setup<W: WorldCommon>(world: &mut W){
+        F::setup(world);
+    }
 }
 
 impl_system_data_multi!{16}
\ No newline at end of file
@@ -0,0 +1,186 @@
+use crate::WorldCommon;
+use crate::system::Scheduler;
+
+use std::time::{Duration, Instant};
+
+/// Frame timing resource, kept up to date by `FixedLoop`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Time{
+    delta: Duration,
+    elapsed: Duration,
+    frame: u64,
+}
+
+impl Time{
+    pub fn new() -> Self{
+        Time::default()
+    }
+
+    /// Time between the start of the last frame and the start of this one
+    pub fn delta(&self) -> Duration{
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f64{
+        self.delta.as_secs_f64()
+    }
+
+    /// Total time simulated since the loop started
+    pub fn elapsed(&self) -> Duration{
+        self.elapsed
+    }
+
+    /// Number of frames run so far, starting at 1 for the first frame
+    pub fn frame(&self) -> u64{
+        self.frame
+    }
+
+    fn advance(&mut self, delta: Duration){
+        self.delta = delta;
+        self.elapsed += delta;
+        self.frame += 1;
+    }
+}
+
+/// Fixed step timing resource, systems in the fixed schedule should use `step` as their dt
+#[derive(Clone, Copy, Debug)]
+pub struct FixedTime{
+    step: Duration,
+    tick: u64,
+    alpha: f64,
+}
+
+impl Default for FixedTime{
+    /// Defaults to 60 steps a second
+    fn default() -> Self{
+        FixedTime::new(Duration::from_nanos(1_000_000_000 / 60))
+    }
+}
+
+impl FixedTime{
+    pub fn new(step: Duration) -> Self{
+        FixedTime{
+            step,
+            tick: 0,
+            alpha: 0.0,
+        }
+    }
+
+    pub fn step(&self) -> Duration{
+        self.step
+    }
+
+    pub fn step_seconds(&self) -> f64{
+        self.step.as_secs_f64()
+    }
+
+    /// Number of fixed steps run so far
+    pub fn tick(&self) -> u64{
+        self.tick
+    }
+
+    /// How far between the last fixed step and the next one we are, in [0, 1)
+    /// Use this to interpolate rendered state between two fixed steps
+    pub fn alpha(&self) -> f64{
+        self.alpha
+    }
+}
+
+/// Drives one schedule every frame and another at a fixed timestep.
+///
+/// Frame time is fed into an accumulator and the fixed schedule runs once for every
+/// whole step in it. At most `max_steps` fixed steps run in a single frame, any time
+/// left over past that is dropped so a slow frame can't snowball into slower ones.
+pub struct FixedLoop<S>{
+    update: S,
+    fixed: S,
+    step: Duration,
+    max_steps: usize,
+    max_delta: Duration,
+    accumulator: Duration,
+    last: Option<Instant>,
+}
+
+impl<S> FixedLoop<S>{
+    pub fn new(update: S, fixed: S, step: Duration) -> Self{
+        assert!(step > Duration::from_secs(0), "Fixed step must be greater than zero");
+        FixedLoop{
+            update,
+            fixed,
+            step,
+            max_steps: 8,
+            max_delta: Duration::from_millis(250),
+            accumulator: Duration::from_secs(0),
+            last: None,
+        }
+    }
+
+    /// Sets the most fixed steps that can run in a single frame
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self{
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Clamps frame deltas to this, so a breakpoint or a hitch doesn't fast forward the simulation
+    pub fn with_max_delta(mut self, max_delta: Duration) -> Self{
+        self.max_delta = max_delta;
+        self
+    }
+
+    pub fn update_schedule(&mut self) -> &mut S{
+        &mut self.update
+    }
+
+    pub fn fixed_schedule(&mut self) -> &mut S{
+        &mut self.fixed
+    }
+
+    /// Inserts the `Time` and `FixedTime` resources the loop updates and sets up both schedules
+    pub fn setup<'d, 'w: 'd, W: WorldCommon>(&self, world: &mut W)
+        where S: Scheduler<'d, 'w, W>{
+        world.insert(Time::new());
+        world.insert(FixedTime::new(self.step));
+        self.update.setup(world);
+        self.fixed.setup(world);
+    }
+
+    /// Runs a frame using the wall clock time since the last call as the delta
+    pub fn tick<'d, 'w: 'd, W: WorldCommon>(&mut self, world: &'w W) -> usize
+        where S: Scheduler<'d, 'w, W>{
+        let now = Instant::now();
+        let delta = match self.last{
+            Some(last) => now - last,
+            None => Duration::from_secs(0),
+        };
+        self.last = Some(now);
+        self.run_frame(world, delta)
+    }
+
+    /// Runs a frame with the given delta, returns how many fixed steps were run.
+    /// Passing the delta in keeps runs deterministic, which `tick` can't be.
+    pub fn run_frame<'d, 'w: 'd, W: WorldCommon>(&mut self, world: &'w W, delta: Duration) -> usize
+        where S: Scheduler<'d, 'w, W>{
+        let delta = std::cmp::min(delta, self.max_delta);
+        world.get_mut::<Time>().advance(delta);
+
+        self.accumulator += delta;
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < self.max_steps{
+            self.fixed.run(world);
+            world.get_mut::<FixedTime>().tick += 1;
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+
+        // Out of catch up steps, drop the whole steps we couldn't run but keep the remainder
+        if self.accumulator >= self.step{
+            let remainder = self.accumulator.as_nanos() % self.step.as_nanos();
+            self.accumulator = Duration::from_nanos(remainder as u64);
+        }
+
+        world.get_mut::<FixedTime>().alpha = self.accumulator.as_secs_f64() / self.step.as_secs_f64();
+
+        self.update.run(world);
+        steps
+    }
+}
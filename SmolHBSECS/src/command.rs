@@ -0,0 +1,63 @@
+use crate::world::World;
+use crate::{Entity, EntityStorage};
+use SmolCommon::WorldCommon;
+use SmolCommon::component::Component;
+
+type Command = Box<dyn FnOnce(&World) + Send + Sync>;
+
+/// Queue of world changes to apply later, systems write to this instead of
+/// changing world structure while other systems are running.
+/// Queued commands are applied between stages, or by calling `Commands::apply`.
+#[derive(Default)]
+pub struct Commands{
+    queue: Vec<Command>,
+}
+
+impl Commands{
+    pub fn new() -> Self{
+        Commands{
+            queue: Vec::new(),
+        }
+    }
+
+    /// Queues an arbitrary change to the world
+    pub fn push<F: 'static + FnOnce(&World) + Send + Sync>(&mut self, command: F){
+        self.queue.push(Box::new(command));
+    }
+
+    /// Queues creating an entity, `build` is called with the new entity so components can be added
+    pub fn spawn<F: 'static + FnOnce(&World, &Entity) + Send + Sync>(&mut self, build: F){
+        self.push(move |world|{
+            let entity = *world.get_mut::<EntityStorage>().create_entity();
+            build(world, &entity);
+        });
+    }
+
+    /// Queues adding a component to an entity
+    pub fn insert<T: 'static + Component>(&mut self, entity: &Entity, comp: T){
+        let index = entity.index;
+        self.push(move |world| world.get_comp_mut::<T>().set(&index, comp));
+    }
+
+    /// Queues removing a component from an entity
+    pub fn remove<T: 'static + Component>(&mut self, entity: &Entity){
+        let index = entity.index;
+        self.push(move |world| world.get_comp_mut::<T>().delete(&index));
+    }
+
+    pub fn len(&self) -> usize{
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.queue.is_empty()
+    }
+
+    /// Applies every command queued in the world's `Commands` resource, in the order they were queued
+    pub fn apply(world: &World){
+        let queue = std::mem::take(&mut world.get_mut::<Commands>().queue);
+        for command in queue{
+            command(world);
+        }
+    }
+}
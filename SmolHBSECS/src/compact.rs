@@ -0,0 +1,154 @@
+use crate::world::World;
+use crate::{Entity, EntityStorage};
+use crate::entity_map::{EntityMap, MapEntities, RemapEvents};
+use crate::hierarchy::{Parent, Children};
+use crate::registry::TypeRegistry;
+use SmolCommon::WorldCommon;
+use std::any::TypeId;
+
+impl World{
+    /// Drops the empty slots at the end of every storage and shrinks them to fit, returning the bytes given back.
+    /// Storages only grow as components get set, so this is worth running after despawning a lot of entities.
+    pub fn compact(&self) -> usize{
+        let before = self.storage_memory().reserved;
+        for (_, storage) in self.storages(){
+            storage.write().shrink();
+        }
+        before - self.storage_memory().reserved
+    }
+
+    /// Moves the live entities to the lowest indices, keeping their order, so the storages have no holes.
+    /// Every handle to an entity that moved goes stale, the returned map has the new handle of every live entity.
+    /// Hierarchy links and components registered `with_map_entities` in the `TypeRegistry` resource are rewritten,
+    /// and the map goes to the `RemapEvents` resource if there is one.
+    pub fn defragment(&self) -> EntityMap{
+        let live = self.get::<EntityStorage>().live_entities();
+        let moves: Vec<(Entity, usize)> = live.into_iter().enumerate().map(|(to, entity)| (entity, to)).collect();
+        let map = self.renumber(&moves);
+        self.compact();
+        map
+    }
+
+    /// Moves every live entity and its components to a new index, see `EntityStorage::renumber`
+    pub(crate) fn renumber(&self, moves: &[(Entity, usize)]) -> EntityMap{
+        let renumbered = self.get_mut::<EntityStorage>().renumber(moves);
+        let mut map = EntityMap::new();
+        let mut table = vec![None; moves.iter().map(|(entity, _)| entity.index + 1).max().unwrap_or(0)];
+        for ((old, to), new) in moves.iter().zip(renumbered.iter()){
+            map.insert(*old, *new);
+            table[old.index] = Some(*to);
+        }
+
+        for (_, storage) in self.storages(){
+            storage.write().remap(&table);
+        }
+
+        if self.has_hierarchy(){
+            for parent in self.get_comp_mut::<Parent>().iter_mut().filter_map(|(_, parent)| parent){
+                parent.map_entities(&map);
+            }
+            for children in self.get_comp_mut::<Children>().iter_mut().filter_map(|(_, children)| children){
+                children.map_entities(&map);
+            }
+        }
+        if self.contains::<TypeRegistry>(){
+            // The hierarchy was just mapped, mapping it again would move links a second time
+            let hierarchy = [TypeId::of::<Parent>(), TypeId::of::<Children>()];
+            let indices: Vec<usize> = renumbered.iter().map(|entity| entity.index).collect();
+            for component in self.get::<TypeRegistry>().components().filter(|component| !hierarchy.contains(&component.type_id())){
+                component.map_entities(self, &map, &indices);
+            }
+        }
+        if self.contains::<RemapEvents>(){
+            self.get_mut::<RemapEvents>().push(map.clone());
+        }
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Target(Entity);
+
+    impl MapEntities for Target{
+        fn map_entities(&mut self, map: &EntityMap){
+            self.0.map_entities(map);
+        }
+    }
+
+    fn world(count: usize) -> (World, Vec<Entity>){
+        let mut world = World::new();
+        world.insert(EntityStorage::new());
+        world.register_hierarchy();
+        world.register_comp::<u32>();
+        world.register_comp::<Target>();
+        let mut registry = TypeRegistry::new();
+        registry.register_comp::<Target>("target").with_map_entities();
+        world.insert(registry);
+        let entities = (0..count).map(|n|{
+            let entity = *world.get_mut::<EntityStorage>().create_entity();
+            world.get_comp_mut::<u32>().set(&entity.index, n as u32);
+            entity
+        }).collect();
+        (world, entities)
+    }
+
+    #[test]
+    fn compact_trims_storages(){
+        let (world, e) = world(1000);
+        for entity in e[10..].iter(){
+            world.despawn(entity);
+        }
+        world.get_comp_mut::<Target>().set(&e[3].index, Target(e[4]));
+
+        let before = world.component_info::<u32>().unwrap().memory;
+        assert!(world.compact() > 0);
+        let after = world.component_info::<u32>().unwrap().memory;
+        assert!(after.reserved < before.reserved / 10);
+        assert!(after.spare() < 8);
+        assert_eq!(world.composition().storages.iter().map(|storage| storage.len).max(), Some(10));
+
+        assert_eq!(*world.get_comp::<u32>().get(&9).unwrap(), 9);
+        assert_eq!(world.component_info::<Target>().unwrap().live, 1);
+        world.get_comp_mut::<u32>().set(&500, 1);
+        world.compact();
+        assert_eq!(*world.get_comp::<u32>().get(&500).unwrap(), 1);
+        assert_eq!(world.compact(), 0);
+    }
+
+    #[test]
+    fn defragment_renumbers(){
+        let (world, e) = world(8);
+        world.set_parent(&e[5], &e[7]);
+        world.set_parent(&e[6], &e[7]);
+        world.get_comp_mut::<Target>().set(&e[7].index, Target(e[6]));
+        for n in [0, 2, 3]{
+            world.despawn(&e[n]);
+        }
+
+        let map = world.defragment();
+        assert_eq!(map.len(), 5);
+        let live = world.get::<EntityStorage>().live_entities();
+        assert_eq!(live.iter().map(|entity| entity.index).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+        for n in [1, 4, 5, 6, 7]{
+            assert_eq!(*world.get_comp::<u32>().get(&map.map(&e[n]).index).unwrap(), n as u32);
+        }
+        assert_eq!(map.map(&e[1]).index, 0);
+        assert_eq!(map.map(&e[7]).index, 4);
+
+        // Moved handles go stale, even the ones that land on an index a despawned handle had
+        assert!(!world.get::<EntityStorage>().is_alive(&e[7]));
+        assert!(!world.get::<EntityStorage>().is_alive(&e[0]) && !world.get::<EntityStorage>().is_alive(&e[3]));
+        assert_eq!(world.parent(&map.map(&e[5])), Some(map.map(&e[7])));
+        assert_eq!(world.children(&map.map(&e[7])), vec![map.map(&e[5]), map.map(&e[6])]);
+        assert_eq!(world.get_comp::<Target>().get(&4), Some(&Target(map.map(&e[6]))));
+        let numbers = world.composition().storages.into_iter().find(|storage| storage.component == "u32").unwrap();
+        assert_eq!((numbers.occupied, numbers.holes()), (5, 0));
+
+        let spawned = *world.get_mut::<EntityStorage>().create_entity();
+        assert_eq!(spawned.index, 5);
+    }
+}
@@ -2,8 +2,12 @@ use SmolCommon::component::*;
 use super::Entity;
 use bit_vec::BitVec;
 use std::iter::FilterMap;
+use std::any::Any;
+use std::alloc::Layout;
+use crate::reflect::{StorageKind, MemoryUsage};
 
 /// Stores components as a normal vector
+#[derive(Clone)]
 pub struct VecStorage<T>{
     storage: Vec<Option<T>>,
     valid: BitVec,
@@ -18,16 +22,151 @@ impl<T> VecStorage<T>{
     }
 }
 
+/// Type erased storage, lets the world work on every storage without knowing the component types
+pub trait AnyStorage: Send + Sync{
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn clone_storage(&self) -> Box<dyn AnyStorage>;
+
+    /// Overwrites this storage with the contents of another storage of the same type, reusing its memory
+    fn copy_from(&mut self, other: &dyn AnyStorage);
+
+    /// Which indices hold a component
+    fn valid(&self) -> &BitVec;
+
+    fn delete_index(&mut self, index: usize);
+
+    fn clear(&mut self);
+
+    /// A new empty storage of the same type
+    fn empty(&self) -> Box<dyn AnyStorage>;
+
+    /// Copies the component at `from` into `to` of another storage of the same type, deleting it there if there's none here
+    fn copy_index(&self, from: usize, dest: &mut dyn AnyStorage, to: usize);
+
+    fn component_name(&self) -> &'static str;
+
+    /// Layout of one component
+    fn layout(&self) -> Layout;
+
+    fn kind(&self) -> StorageKind;
+
+    /// How many indices hold a component
+    fn live(&self) -> usize;
+
+    fn memory(&self) -> MemoryUsage;
+
+    /// Drops the empty slots after the last component and gives the spare memory back
+    fn shrink(&mut self);
+
+    /// Moves the component at every index `i` to `table[i]`, dropping it if that's None or `i` is past the end of the table
+    fn remap(&mut self, table: &[Option<usize>]);
+}
+
+impl<T: 'static + Component> AnyStorage for VecStorage<T>{
+    fn as_any(&self) -> &dyn Any{
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any{
+        self
+    }
+
+    fn clone_storage(&self) -> Box<dyn AnyStorage>{
+        Box::new(self.clone())
+    }
+
+    fn copy_from(&mut self, other: &dyn AnyStorage){
+        let other = other.as_any().downcast_ref::<VecStorage<T>>().unwrap();
+        self.storage.clone_from(&other.storage);
+        self.valid.clone_from(&other.valid);
+    }
+
+    fn valid(&self) -> &BitVec{
+        &self.valid
+    }
+
+    fn delete_index(&mut self, index: usize){
+        self.delete(&index);
+    }
+
+    fn clear(&mut self){
+        self.storage.clear();
+        self.valid = BitVec::new();
+    }
+
+    fn empty(&self) -> Box<dyn AnyStorage>{
+        Box::new(VecStorage::<T>::new())
+    }
+
+    fn copy_index(&self, from: usize, dest: &mut dyn AnyStorage, to: usize){
+        let dest = dest.as_any_mut().downcast_mut::<VecStorage<T>>().unwrap();
+        match self.get(&from){
+            Some(comp) => dest.set(&to, *comp),
+            None => dest.delete(&to),
+        }
+    }
+
+    fn component_name(&self) -> &'static str{
+        std::any::type_name::<T>()
+    }
+
+    fn layout(&self) -> Layout{
+        Layout::new::<T>()
+    }
+
+    fn kind(&self) -> StorageKind{
+        StorageKind::Vec
+    }
+
+    fn live(&self) -> usize{
+        self.valid.iter().filter(|valid| *valid).count()
+    }
+
+    // Every slot is an Option<T> whether it's used or not, plus a bit in the validity bitset
+    fn memory(&self) -> MemoryUsage{
+        let slot = std::mem::size_of::<Option<T>>();
+        MemoryUsage{
+            used: self.storage.len() * slot + self.valid.len().div_ceil(8),
+            reserved: self.storage.capacity() * slot + self.valid.capacity().div_ceil(8),
+        }
+    }
+
+    fn shrink(&mut self){
+        let len = self.valid.iter().rposition(|valid| valid).map_or(0, |last| last + 1);
+        self.storage.truncate(len);
+        self.valid.truncate(len);
+        self.storage.shrink_to_fit();
+        self.valid.shrink_to_fit();
+    }
+
+    fn remap(&mut self, table: &[Option<usize>]){
+        let len = table.iter().flatten().map(|to| to + 1).max().unwrap_or(0);
+        let mut storage = vec![None; len];
+        let mut valid = BitVec::from_elem(len, false);
+        for (from, comp) in self.storage.iter().enumerate(){
+            if let (Some(comp), Some(Some(to))) = (comp, table.get(from)){
+                storage[*to] = Some(*comp);
+                valid.set(*to, true);
+            }
+        }
+        self.storage = storage;
+        self.valid = valid;
+    }
+}
+
 impl<T: Component> ComponentStorage<T> for VecStorage<T>{
 
     /// Gets a reference to a component at the given index (entity)
     fn get<'cs>(&'cs self, entity: &usize) -> Option<&'cs T>{
-        return self.storage.get(*entity).unwrap().as_ref();
+        return self.storage.get(*entity).and_then(|comp| comp.as_ref());
     }
 
     /// Gets a mutable reference to a component at the given index (entity)
     fn get_mut<'cs>(&'cs mut self, entity: &usize) -> Option<&'cs mut T>{
-        return self.storage.get_mut(*entity).unwrap().as_mut();
+        return self.storage.get_mut(*entity).and_then(|comp| comp.as_mut());
     }
 
     /// Iterates over the valid components.
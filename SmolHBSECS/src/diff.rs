@@ -0,0 +1,434 @@
+use crate::world::World;
+use crate::Entity;
+use crate::component::AnyStorage;
+use crate::registry::TypeRegistry;
+use crate::snapshot::Snapshot;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use crate::EntityStorage;
+#[cfg(feature = "serde")]
+use crate::serialize::{SerializeError, component_serde};
+#[cfg(feature = "serde")]
+use SmolCommon::WorldCommon;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+#[cfg(feature = "serde")]
+use serde_json::Value;
+
+/// Printed in place of values of components that didn't opt into `with_debug`
+pub const NO_DEBUG: &str = "<no Debug>";
+
+/// What happened to one component of one entity, values are formatted with the registered `Debug`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change{
+    Added(String),
+    Removed(String),
+    Changed(String, String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComponentChange{
+    pub entity: Entity,
+    /// Registered name of the component
+    pub component: String,
+    pub change: Change,
+}
+
+/// Differences between two world states, ordered by entity index.
+/// Only components in the registry are compared, and values only count as changed
+/// for components that opted into `with_eq`.
+#[derive(Clone, Debug, Default)]
+pub struct WorldDiff{
+    pub spawned: Vec<Entity>,
+    pub despawned: Vec<Entity>,
+    pub changes: Vec<ComponentChange>,
+}
+
+impl WorldDiff{
+    pub fn is_empty(&self) -> bool{
+        self.spawned.is_empty() && self.despawned.is_empty() && self.changes.is_empty()
+    }
+}
+
+fn entity_name(entity: &Entity) -> String{
+    format!("{}v{}", entity.index, entity.generation)
+}
+
+impl fmt::Display for WorldDiff{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        for entity in self.despawned.iter(){
+            writeln!(f, "- entity {}", entity_name(entity))?;
+        }
+        for entity in self.spawned.iter(){
+            writeln!(f, "+ entity {}", entity_name(entity))?;
+        }
+        for change in self.changes.iter(){
+            let entity = entity_name(&change.entity);
+            match &change.change{
+                Change::Added(value) => writeln!(f, "+ {} {}: {}", entity, change.component, value)?,
+                Change::Removed(value) => writeln!(f, "- {} {}: {}", entity, change.component, value)?,
+                Change::Changed(before, after) => writeln!(f, "~ {} {}: {} -> {}", entity, change.component, before, after)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn valid(storage: &dyn AnyStorage, index: usize) -> bool{
+    storage.valid().get(index).unwrap_or(false)
+}
+
+// Live entities by index, a snapshot without entities treats every index holding a registered component as an entity
+fn live(snapshot: &Snapshot, registry: &TypeRegistry) -> BTreeMap<usize, Entity>{
+    if let Some(entities) = snapshot.entities(){
+        return entities.live_entities().into_iter().map(|entity| (entity.index, entity)).collect();
+    }
+
+    let mut live = BTreeMap::new();
+    for component in registry.components(){
+        if let Some(storage) = snapshot.storage(&component.type_id()){
+            for index in (0..storage.valid().len()).filter(|&index| valid(storage, index)){
+                live.insert(index, Entity{index, generation: 0});
+            }
+        }
+    }
+    live
+}
+
+fn format(value: Option<String>) -> String{
+    value.unwrap_or_else(|| NO_DEBUG.to_string())
+}
+
+impl Snapshot{
+    /// Everything that differs going from this snapshot to `after`
+    pub fn diff(&self, after: &Snapshot, registry: &TypeRegistry) -> WorldDiff{
+        let before_live = live(self, registry);
+        let after_live = live(after, registry);
+        let indices: BTreeSet<usize> = before_live.keys().chain(after_live.keys()).copied().collect();
+
+        let mut diff = WorldDiff::default();
+        for index in indices{
+            let old = before_live.get(&index);
+            let new = after_live.get(&index);
+            let same = old.is_some() && old == new;
+            if !same{
+                diff.despawned.extend(old);
+                diff.spawned.extend(new);
+            }
+
+            for component in registry.components(){
+                let id = component.type_id();
+                let before = old.and(self.storage(&id)).filter(|storage| valid(*storage, index));
+                let now = new.and(after.storage(&id)).filter(|storage| valid(*storage, index));
+                let mut push = |entity: &Entity, change: Change| diff.changes.push(ComponentChange{
+                    entity: *entity,
+                    component: component.name().to_string(),
+                    change,
+                });
+
+                match (before, now){
+                    (Some(before), Some(now)) if same => {
+                        if component.eq(before, now, index) == Some(false){
+                            push(old.unwrap(), Change::Changed(format(component.debug(before, index)), format(component.debug(now, index))));
+                        }
+                    },
+                    (before, now) => {
+                        if let Some(before) = before{
+                            push(old.unwrap(), Change::Removed(format(component.debug(before, index))));
+                        }
+                        if let Some(now) = now{
+                            push(new.unwrap(), Change::Added(format(component.debug(now, index))));
+                        }
+                    },
+                }
+            }
+        }
+        diff
+    }
+}
+
+impl World{
+    /// Everything that differs going from this world to `after`, see `Snapshot::diff`
+    pub fn diff(&self, after: &World, registry: &TypeRegistry) -> WorldDiff{
+        self.snapshot().diff(&after.snapshot(), registry)
+    }
+}
+
+/// A diff with the new values, applying it to a world in the before state brings it to the after state.
+/// Components need `with_serde` to have their values in a patch, added or changed ones without it are listed in `skipped`.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+pub struct Patch{
+    /// The entities of the after state, only there if entities were spawned or despawned
+    entities: Option<EntityStorage>,
+    despawned: Vec<usize>,
+    removed: BTreeMap<String, Vec<usize>>,
+    /// Added and changed components, in the format of `World::save_json`
+    set: BTreeMap<String, Value>,
+    /// Registered components that were added or changed but have no serde, so their values aren't in the patch
+    #[serde(default)]
+    skipped: Vec<String>,
+}
+
+#[cfg(feature = "serde")]
+impl Patch{
+    pub fn to_json(&self) -> Result<String, SerializeError>{
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(data: &str) -> Result<Patch, SerializeError>{
+        Ok(serde_json::from_str(data)?)
+    }
+
+    /// Whether applying the patch changes nothing, skipped components don't count
+    pub fn is_empty(&self) -> bool{
+        self.entities.is_none() && self.removed.is_empty() && self.set.is_empty()
+    }
+
+    /// Names of the components whose new values couldn't be saved, in order
+    pub fn skipped(&self) -> &[String]{
+        &self.skipped
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Snapshot{
+    /// Diffs this snapshot against `after` and saves the new values so the diff can be applied with `World::apply_patch`
+    pub fn patch(&self, after: &Snapshot, registry: &TypeRegistry) -> Result<Patch, SerializeError>{
+        let diff = self.diff(after, registry);
+        let mut removed: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        let mut set: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+
+        for change in diff.changes.iter(){
+            let indices = match change.change{
+                Change::Removed(_) => removed.entry(change.component.clone()).or_default(),
+                _ => set.entry(change.component.clone()).or_default(),
+            };
+            indices.push(change.entity.index);
+        }
+
+        let mut patch = Patch{
+            entities: None,
+            despawned: diff.despawned.iter().map(|entity| entity.index).collect(),
+            removed,
+            set: BTreeMap::new(),
+            skipped: Vec::new(),
+        };
+        if !diff.spawned.is_empty() || !diff.despawned.is_empty(){
+            patch.entities = after.entities().cloned();
+        }
+
+        for (name, indices) in set{
+            // Diffs only report registered components, so a missing serde is the only way this fails
+            let serde = match registry.component(&name).and_then(|component| component.serde.as_ref()){
+                Some(serde) => serde,
+                None => {
+                    patch.skipped.push(name);
+                    continue;
+                },
+            };
+            let storage = after.storage(&registry.component(&name).unwrap().type_id()).unwrap();
+            patch.set.insert(name, (serde.entries_to_json)(storage, &indices)?);
+        }
+        Ok(patch)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl World{
+    /// Diffs this world against `after`, see `Snapshot::patch`
+    pub fn patch(&self, after: &World, registry: &TypeRegistry) -> Result<Patch, SerializeError>{
+        self.snapshot().patch(&after.snapshot(), registry)
+    }
+
+    /// Applies a patch made against the state this world is in.
+    /// Every entry is checked and loaded before anything changes, so an error leaves the world as it was.
+    pub fn apply_patch(&mut self, registry: &TypeRegistry, patch: &Patch) -> Result<(), SerializeError>{
+        let removed = patch.removed.iter()
+            .map(|(name, indices)|{
+                let component = registry.component(name).ok_or_else(|| SerializeError::UnknownComponent(name.clone()))?;
+                Ok((component.type_id(), indices))
+            })
+            .collect::<Result<Vec<_>, SerializeError>>()?;
+        let set = patch.set.iter()
+            .map(|(name, value)|{
+                let serde = component_serde(registry, name)?;
+                Ok((registry.component(name).unwrap().type_id(), (serde.entries_from_json)(value.clone())?))
+            })
+            .collect::<Result<Vec<_>, SerializeError>>()?;
+
+        registry.register_all(self);
+
+        if let Some(entities) = &patch.entities{
+            if self.contains::<EntityStorage>(){
+                self.get_mut::<EntityStorage>().clone_from(entities);
+            }
+            else{
+                self.insert(entities.clone());
+            }
+        }
+
+        for (_, storage) in self.storages(){
+            let mut storage = storage.write();
+            for index in patch.despawned.iter(){
+                storage.delete_index(*index);
+            }
+        }
+
+        for (id, indices) in removed{
+            let mut storage = self.storage(&id).unwrap().write();
+            for index in indices{
+                storage.delete_index(*index);
+            }
+        }
+
+        for (id, entries) in set{
+            let mut storage = self.storage(&id).unwrap().write();
+            for index in entries.valid().iter().enumerate().filter(|(_, valid)| *valid).map(|(index, _)| index){
+                entries.copy_index(index, &mut **storage, index);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::EntityStorage;
+    use SmolCommon::WorldCommon;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct Position{
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Clone, Copy)]
+    struct Tag;
+
+    fn registry() -> TypeRegistry{
+        let mut registry = TypeRegistry::new();
+        #[cfg(feature = "serde")]
+        registry.register_comp::<Position>("Position").with_debug().with_eq().with_serde();
+        #[cfg(not(feature = "serde"))]
+        registry.register_comp::<Position>("Position").with_debug().with_eq();
+        registry.register_comp::<Tag>("Tag");
+        registry
+    }
+
+    fn world() -> World{
+        let mut world = World::new();
+        world.insert(EntityStorage::new());
+        registry().register_all(&mut world);
+        for i in 0..4{
+            let entity = *world.get_mut::<EntityStorage>().create_entity();
+            world.get_comp_mut::<Position>().set(&entity.index, Position{x: i, y: 0});
+        }
+        world
+    }
+
+    fn change(world: &World){
+        let mut entities = world.get_mut::<EntityStorage>();
+        let despawned = entities.live_entities()[1];
+        entities.delete_entity(&despawned);
+        world.get_comp_mut::<Position>().delete(&despawned.index);
+        world.get_comp_mut::<Position>().get_mut(&2).unwrap().y = 5;
+        world.get_comp_mut::<Position>().delete(&3);
+        world.get_comp_mut::<Tag>().set(&0, Tag);
+
+        // Reuses index 1 with the next generation
+        let spawned = *entities.create_entity();
+        world.get_comp_mut::<Position>().set(&spawned.index, Position{x: 9, y: 9});
+    }
+
+    #[test]
+    fn diff_reports_changes(){
+        let registry = registry();
+        let before = world();
+        let after = world();
+        assert!(before.diff(&after, &registry).is_empty());
+
+        change(&after);
+        let diff = before.diff(&after, &registry);
+
+        assert!(diff.despawned == vec![Entity{index: 1, generation: 0}]);
+        assert!(diff.spawned == vec![Entity{index: 1, generation: 1}]);
+        assert_eq!(diff.changes.len(), 5);
+        assert_eq!(diff.changes[0].change, Change::Added(NO_DEBUG.to_string()));
+        assert_eq!(diff.changes[3].change, Change::Changed("Position { x: 2, y: 0 }".to_string(), "Position { x: 2, y: 5 }".to_string()));
+
+        let text = diff.to_string();
+        assert!(text.contains("- entity 1v0"));
+        assert!(text.contains("+ 0v0 Tag: <no Debug>"));
+        assert!(text.contains("- 1v0 Position: Position { x: 1, y: 0 }"));
+        assert!(text.contains("+ 1v1 Position: Position { x: 9, y: 9 }"));
+        assert!(text.contains("- 3v0 Position: Position { x: 3, y: 0 }"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn patch_round_trip(){
+        let registry = registry();
+        let before = world();
+        let after = world();
+        after.get_comp_mut::<Position>().get_mut(&2).unwrap().y = 5;
+        after.get_comp_mut::<Position>().delete(&3);
+        let spawned = *after.get_mut::<EntityStorage>().create_entity();
+        after.get_comp_mut::<Position>().set(&spawned.index, Position{x: 9, y: 9});
+
+        let json = before.patch(&after, &registry).unwrap().to_json().unwrap();
+        let patch = Patch::from_json(&json).unwrap();
+
+        let mut target = world();
+        target.apply_patch(&registry, &patch).unwrap();
+        assert!(target.diff(&after, &registry).is_empty());
+        assert!(target.get::<EntityStorage>().is_alive(&spawned));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn bad_patch_changes_nothing(){
+        let registry = registry();
+        let before = world();
+        let after = world();
+        after.get_comp_mut::<Position>().delete(&0);
+        after.get_comp_mut::<Position>().get_mut(&2).unwrap().y = 5;
+        let spawned = *after.get_mut::<EntityStorage>().create_entity();
+        after.get_comp_mut::<Position>().set(&spawned.index, Position{x: 9, y: 9});
+
+        let mut json: serde_json::Value = serde_json::from_str(&before.patch(&after, &registry).unwrap().to_json().unwrap()).unwrap();
+        json["set"]["Position"][0][1]["y"] = serde_json::json!("five");
+        let patch = Patch::from_json(&json.to_string()).unwrap();
+
+        let mut target = world();
+        assert!(matches!(target.apply_patch(&registry, &patch), Err(SerializeError::Json(_))));
+        assert!(target.diff(&before, &registry).is_empty());
+        assert_eq!(target.get::<EntityStorage>().live_entities().len(), 4);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn patch_skips_components_without_serde(){
+        let registry = registry();
+        let before = world();
+        let after = world();
+        before.get_comp_mut::<Tag>().set(&3, Tag);
+        after.get_comp_mut::<Position>().get_mut(&2).unwrap().y = 5;
+        after.get_comp_mut::<Tag>().set(&0, Tag);
+
+        let patch = Patch::from_json(&before.patch(&after, &registry).unwrap().to_json().unwrap()).unwrap();
+        assert_eq!(patch.skipped(), ["Tag".to_string()]);
+
+        // Removing needs no serde, so only the added Tag is missing afterwards
+        let mut target = world();
+        target.get_comp_mut::<Tag>().set(&3, Tag);
+        target.apply_patch(&registry, &patch).unwrap();
+        let diff = target.diff(&after, &registry);
+        assert_eq!(diff.changes.len(), 1);
+        assert!(diff.changes[0].component == "Tag" && diff.changes[0].entity.index == 0);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::Entity;
+use std::collections::HashMap;
+
+/// Maps entities from one numbering to another, like saved entities to the ones they were loaded as
+#[derive(Clone, Debug, Default)]
+pub struct EntityMap{
+    map: HashMap<Entity, Entity>,
+}
+
+impl EntityMap{
+    pub fn new() -> Self{
+        EntityMap{
+            map: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, from: Entity, to: Entity){
+        self.map.insert(from, to);
+    }
+
+    pub fn get(&self, entity: &Entity) -> Option<Entity>{
+        self.map.get(entity).copied()
+    }
+
+    /// The entity this one was mapped to, or the same entity if it wasn't mapped
+    pub fn map(&self, entity: &Entity) -> Entity{
+        self.get(entity).unwrap_or(*entity)
+    }
+
+    pub fn len(&self) -> usize{
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.map.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Entity, &Entity)>{
+        self.map.iter()
+    }
+}
+
+/// Collects the map of every renumbering of the world it's a resource of, from `World::defragment` and
+/// `World::reorder_by_key`, so code holding on to entities can bring them up to date
+#[derive(Clone, Debug, Default)]
+pub struct RemapEvents{
+    events: Vec<EntityMap>,
+}
+
+impl RemapEvents{
+    pub fn new() -> Self{
+        RemapEvents::default()
+    }
+
+    pub fn events(&self) -> &[EntityMap]{
+        &self.events
+    }
+
+    /// Takes the events since the last call
+    pub fn drain_events(&mut self) -> Vec<EntityMap>{
+        std::mem::take(&mut self.events)
+    }
+
+    /// Runs an entity from before the oldest event through every event since
+    pub fn map(&self, entity: &Entity) -> Entity{
+        self.events.iter().fold(*entity, |entity, map| map.map(&entity))
+    }
+
+    pub(crate) fn push(&mut self, map: EntityMap){
+        self.events.push(map);
+    }
+}
+
+/// Components that store entities implement this so the references can be fixed up
+/// when entities get renumbered, opt in with `ComponentBuilder::with_map_entities`
+pub trait MapEntities{
+    fn map_entities(&mut self, map: &EntityMap);
+}
+
+impl MapEntities for Entity{
+    fn map_entities(&mut self, map: &EntityMap){
+        *self = map.map(self);
+    }
+}
+
+impl MapEntities for Option<Entity>{
+    fn map_entities(&mut self, map: &EntityMap){
+        if let Some(entity) = self{
+            entity.map_entities(map);
+        }
+    }
+}
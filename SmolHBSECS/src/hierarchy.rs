@@ -0,0 +1,372 @@
+use crate::world::World;
+use crate::{Entity, EntityStorage};
+use crate::entity_map::{EntityMap, MapEntities};
+use SmolCommon::WorldCommon;
+use SmolCommon::component::ComponentStorage;
+use std::collections::HashSet;
+
+/// The entity this one is a child of.
+/// Children are kept as a linked list through their `Parent` components since components have to be Copy,
+/// so change these through `World::set_parent` and `World::remove_parent` rather than setting them directly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Parent{
+    parent: Entity,
+    prev: Option<Entity>,
+    next: Option<Entity>,
+}
+
+impl Parent{
+    pub fn get(&self) -> Entity{
+        self.parent
+    }
+
+    /// The next child of the same parent
+    pub fn next_sibling(&self) -> Option<Entity>{
+        self.next
+    }
+
+    pub fn prev_sibling(&self) -> Option<Entity>{
+        self.prev
+    }
+}
+
+/// Head of the list of an entity's children, only there while the entity has children
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Children{
+    first: Option<Entity>,
+    last: Option<Entity>,
+    len: usize,
+}
+
+impl Children{
+    pub fn first(&self) -> Option<Entity>{
+        self.first
+    }
+
+    pub fn len(&self) -> usize{
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.len == 0
+    }
+}
+
+impl MapEntities for Parent{
+    fn map_entities(&mut self, map: &EntityMap){
+        self.parent.map_entities(map);
+        self.prev.map_entities(map);
+        self.next.map_entities(map);
+    }
+}
+
+impl MapEntities for Children{
+    fn map_entities(&mut self, map: &EntityMap){
+        self.first.map_entities(map);
+        self.last.map_entities(map);
+    }
+}
+
+type Parents<'a> = dyn ComponentStorage<Parent> + 'a;
+type ChildLists<'a> = dyn ComponentStorage<Children> + 'a;
+
+fn child_list(parents: &Parents, children: &ChildLists, index: usize) -> Vec<Entity>{
+    let mut list = Vec::new();
+    let mut next = children.get(&index).and_then(|children| children.first);
+    while let Some(child) = next{
+        list.push(child);
+        next = parents.get(&child.index).and_then(|parent| parent.next);
+    }
+    list
+}
+
+// Unlinks the entity from its parent's list of children
+fn detach(parents: &mut Parents, children: &mut ChildLists, index: usize){
+    let link = match parents.get(&index){
+        Some(link) => *link,
+        None => return,
+    };
+    let parent = link.parent.index;
+
+    match link.prev{
+        Some(prev) => parents.get_mut(&prev.index).unwrap().next = link.next,
+        None => children.get_mut(&parent).unwrap().first = link.next,
+    }
+    match link.next{
+        Some(next) => parents.get_mut(&next.index).unwrap().prev = link.prev,
+        None => children.get_mut(&parent).unwrap().last = link.prev,
+    }
+
+    let list = children.get_mut(&parent).unwrap();
+    list.len -= 1;
+    if list.len == 0{
+        children.delete(&parent);
+    }
+    parents.delete(&index);
+}
+
+// Adds the entity to the end of the parent's list of children
+fn attach(parents: &mut Parents, children: &mut ChildLists, child: Entity, parent: Entity){
+    let last = children.get(&parent.index).and_then(|list| list.last);
+    parents.set(&child.index, Parent{
+        parent,
+        prev: last,
+        next: None,
+    });
+
+    match last{
+        Some(last) => parents.get_mut(&last.index).unwrap().next = Some(child),
+        None => children.set(&parent.index, Children{
+            first: Some(child),
+            last: None,
+            len: 0,
+        }),
+    }
+    let list = children.get_mut(&parent.index).unwrap();
+    list.last = Some(child);
+    list.len += 1;
+}
+
+impl World{
+    /// Registers the `Parent` and `Children` storages if they aren't there yet
+    pub fn register_hierarchy(&mut self){
+        if !self.contains_comp::<Parent>(){
+            self.register_comp::<Parent>();
+        }
+        if !self.contains_comp::<Children>(){
+            self.register_comp::<Children>();
+        }
+    }
+
+    pub(crate) fn has_hierarchy(&self) -> bool{
+        self.contains_comp::<Parent>() && self.contains_comp::<Children>()
+    }
+
+    /// Makes `child` the last child of `parent`, moving it out of its old parent's children.
+    /// Panics if either entity is dead or this would make an entity its own ancestor.
+    pub fn set_parent(&self, child: &Entity, parent: &Entity){
+        {
+            let entities = self.get::<EntityStorage>();
+            assert!(entities.is_alive(child), "Entity {} isn't alive", child.index);
+            assert!(entities.is_alive(parent), "Entity {} isn't alive", parent.index);
+        }
+        let mut parents = self.get_comp_mut::<Parent>();
+        let mut children = self.get_comp_mut::<Children>();
+
+        let mut ancestor = Some(*parent);
+        while let Some(entity) = ancestor{
+            assert!(entity != *child, "Entity {} can't be a descendant of itself", child.index);
+            ancestor = parents.get(&entity.index).map(|link| link.parent);
+        }
+
+        detach(&mut *parents, &mut *children, child.index);
+        attach(&mut *parents, &mut *children, *child, *parent);
+    }
+
+    /// Makes the entity a root again
+    pub fn remove_parent(&self, child: &Entity){
+        let mut parents = self.get_comp_mut::<Parent>();
+        let mut children = self.get_comp_mut::<Children>();
+        detach(&mut *parents, &mut *children, child.index);
+    }
+
+    pub fn parent(&self, child: &Entity) -> Option<Entity>{
+        if !self.contains_comp::<Parent>(){
+            return None;
+        }
+        self.get_comp::<Parent>().get(&child.index).map(|link| link.parent)
+    }
+
+    /// The entity's children in the order they were added
+    pub fn children(&self, parent: &Entity) -> Vec<Entity>{
+        if !self.has_hierarchy(){
+            return Vec::new();
+        }
+        child_list(&*self.get_comp::<Parent>(), &*self.get_comp::<Children>(), parent.index)
+    }
+
+    /// Every entity under this one, parents before their children
+    pub fn descendants(&self, entity: &Entity) -> Vec<Entity>{
+        if !self.has_hierarchy(){
+            return Vec::new();
+        }
+        let parents = self.get_comp::<Parent>();
+        let children = self.get_comp::<Children>();
+
+        let mut descendants = Vec::new();
+        let mut stack = child_list(&*parents, &*children, entity.index);
+        stack.reverse();
+        while let Some(next) = stack.pop(){
+            descendants.push(next);
+            stack.extend(child_list(&*parents, &*children, next.index).into_iter().rev());
+        }
+        descendants
+    }
+
+    /// Deletes the live ones of the entities and all of their components, locking every storage once.
+    /// Hierarchy links to them are left as they are.
+    pub(crate) fn delete_entities(&self, entities: &[Entity]){
+        let alive: Vec<Entity> = {
+            let storage = self.get::<EntityStorage>();
+            entities.iter().filter(|entity| storage.is_alive(entity)).copied().collect()
+        };
+        for (_, storage) in self.storages(){
+            let mut storage = storage.write();
+            for entity in alive.iter(){
+                storage.delete_index(entity.index);
+            }
+        }
+        let mut storage = self.get_mut::<EntityStorage>();
+        for entity in alive.iter(){
+            storage.delete_entity(entity);
+        }
+    }
+
+    /// Deletes an entity along with all of its descendants and their components, returns false if it was already dead.
+    /// The entity is taken out of its parent's children.
+    pub fn despawn(&self, entity: &Entity) -> bool{
+        self.despawn_batch(std::slice::from_ref(entity)) > 0
+    }
+
+    /// Despawns the live ones of the entities along with their descendants in one pass over the storages,
+    /// returns how many entities went in total
+    pub fn despawn_batch(&self, entities: &[Entity]) -> usize{
+        let mut despawned: Vec<Entity> = {
+            let storage = self.get::<EntityStorage>();
+            entities.iter().filter(|entity| storage.is_alive(entity)).copied().collect()
+        };
+
+        if self.has_hierarchy(){
+            let mut seen: HashSet<Entity> = despawned.iter().copied().collect();
+            for entity in despawned.clone(){
+                despawned.extend(self.descendants(&entity).into_iter().filter(|descendant| seen.insert(*descendant)));
+            }
+            // Links inside the batch go with the storages, only the ones to a parent that stays need undoing
+            let mut parents = self.get_comp_mut::<Parent>();
+            let mut children = self.get_comp_mut::<Children>();
+            for entity in despawned.iter(){
+                if parents.get(&entity.index).is_some_and(|link| !seen.contains(&link.parent)){
+                    detach(&mut *parents, &mut *children, entity.index);
+                }
+            }
+        }
+        self.delete_entities(&despawned);
+        despawned.len()
+    }
+
+    /// Same as `despawn`, which already takes the descendants with it
+    pub fn despawn_recursive(&self, entity: &Entity) -> bool{
+        self.despawn(entity)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn world(count: usize) -> (World, Vec<Entity>){
+        let mut world = World::new();
+        world.insert(EntityStorage::new());
+        world.register_hierarchy();
+        world.register_comp::<u32>();
+        let entities = (0..count).map(|n|{
+            let entity = *world.get_mut::<EntityStorage>().create_entity();
+            world.get_comp_mut::<u32>().set(&entity.index, n as u32);
+            entity
+        }).collect();
+        (world, entities)
+    }
+
+    #[test]
+    fn reparent(){
+        let (world, e) = world(5);
+        world.set_parent(&e[1], &e[0]);
+        world.set_parent(&e[2], &e[0]);
+        world.set_parent(&e[3], &e[0]);
+        assert!(world.children(&e[0]) == vec![e[1], e[2], e[3]]);
+
+        // Moving out of the middle of the list
+        world.set_parent(&e[2], &e[4]);
+        assert!(world.children(&e[0]) == vec![e[1], e[3]]);
+        assert!(world.children(&e[4]) == vec![e[2]]);
+        assert!(world.parent(&e[2]) == Some(e[4]));
+
+        world.remove_parent(&e[1]);
+        world.remove_parent(&e[3]);
+        assert!(world.children(&e[0]).is_empty());
+        assert!(world.get_comp::<Children>().get(&e[0].index).is_none());
+        assert!(world.parent(&e[1]).is_none());
+
+        world.set_parent(&e[4], &e[0]);
+        assert!(world.descendants(&e[0]) == vec![e[4], e[2]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't alive")]
+    fn stale_parent(){
+        let (world, e) = world(2);
+        world.despawn(&e[0]);
+        let reused = *world.get_mut::<EntityStorage>().create_entity();
+        assert_eq!(reused.index, e[0].index);
+        world.set_parent(&e[1], &e[0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn cycle(){
+        let (world, e) = world(3);
+        world.set_parent(&e[1], &e[0]);
+        world.set_parent(&e[2], &e[1]);
+        world.set_parent(&e[0], &e[2]);
+    }
+
+    #[test]
+    fn despawn(){
+        let (world, e) = world(5);
+        world.set_parent(&e[1], &e[0]);
+        world.set_parent(&e[2], &e[1]);
+        world.set_parent(&e[3], &e[1]);
+
+        assert!(world.despawn(&e[0]));
+        assert!(!world.despawn(&e[0]));
+        let entities = world.get::<EntityStorage>();
+        assert!(!entities.is_alive(&e[1]));
+        assert!(!entities.is_alive(&e[2]) && !entities.is_alive(&e[3]));
+        assert!(entities.live_entities() == vec![e[4]]);
+        assert!(world.get_comp::<u32>().get(&e[2].index).is_none());
+        assert!(world.get_comp::<Parent>().iter().all(|(valid, _)| !valid));
+    }
+
+    #[test]
+    fn despawn_batch(){
+        let (world, e) = world(5);
+        world.set_parent(&e[1], &e[0]);
+        world.set_parent(&e[2], &e[1]);
+        world.set_parent(&e[3], &e[0]);
+
+        // e[2] comes along with e[1] and is only counted once
+        assert_eq!(world.despawn_batch(&[e[1], e[2], e[4]]), 3);
+        assert_eq!(world.despawn_batch(&[e[1]]), 0);
+        assert!(world.get::<EntityStorage>().live_entities() == vec![e[0], e[3]]);
+        assert!(world.children(&e[0]) == vec![e[3]]);
+        assert!(world.get_comp::<u32>().get(&e[2].index).is_none());
+    }
+
+    #[test]
+    fn despawn_recursive(){
+        let (world, e) = world(5);
+        world.set_parent(&e[1], &e[0]);
+        world.set_parent(&e[2], &e[1]);
+        world.set_parent(&e[3], &e[1]);
+        world.set_parent(&e[4], &e[0]);
+
+        assert!(world.despawn_recursive(&e[1]));
+        assert!(world.children(&e[0]) == vec![e[4]]);
+        let entities = world.get::<EntityStorage>();
+        assert!(entities.live_entities() == vec![e[0], e[4]]);
+        assert!(world.get_comp::<u32>().get(&e[3].index).is_none());
+        assert!(world.get_comp::<Parent>().get(&e[2].index).is_none());
+    }
+}
@@ -0,0 +1,184 @@
+use crate::world::World;
+use crate::{Entity, EntityStorage};
+use crate::component::AnyStorage;
+use crate::diff::NO_DEBUG;
+use crate::registry::TypeRegistry;
+use SmolCommon::WorldCommon;
+use std::any::TypeId;
+use std::fmt;
+
+/// One component an inspected entity has
+#[derive(Clone, Debug, PartialEq)]
+pub struct InspectedComponent{
+    pub type_name: &'static str,
+    /// Name in the `TypeRegistry` resource, None if there's no registry or the type isn't in it
+    pub registered: Option<String>,
+    /// Formatted with the registered `Debug`, None if the type didn't opt into `with_debug`
+    pub value: Option<String>,
+}
+
+impl InspectedComponent{
+    /// Registered name if there is one, type name otherwise
+    pub fn name(&self) -> &str{
+        self.registered.as_deref().unwrap_or(self.type_name)
+    }
+}
+
+/// Every component of one entity, from `World::inspect`
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityInspection{
+    pub entity: Entity,
+    /// False for a stale handle, which then has no components
+    pub alive: bool,
+    /// In type name order
+    pub components: Vec<InspectedComponent>,
+}
+
+impl fmt::Display for EntityInspection{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        if !self.alive{
+            return writeln!(f, "entity {}v{} (dead)", self.entity.index, self.entity.generation);
+        }
+        writeln!(f, "entity {}v{}: {} components", self.entity.index, self.entity.generation, self.components.len())?;
+        for component in self.components.iter(){
+            writeln!(f, "  {}: {}", component.name(), component.value.as_deref().unwrap_or(NO_DEBUG))?;
+        }
+        Ok(())
+    }
+}
+
+fn valid(storage: &dyn AnyStorage, index: usize) -> bool{
+    storage.valid().get(index).unwrap_or(false)
+}
+
+impl World{
+    // Without an EntityStorage every index counts as alive
+    fn inspectable(&self, entity: &Entity) -> bool{
+        !self.contains::<EntityStorage>() || self.get::<EntityStorage>().is_alive(entity)
+    }
+
+    // Storages with a component at the entity's index, in type name order
+    fn storages_of(&self, entity: &Entity) -> Vec<(TypeId, &'static str)>{
+        if !self.inspectable(entity){
+            return Vec::new();
+        }
+        let mut found: Vec<(TypeId, &'static str)> = self.storages()
+            .filter_map(|(id, storage)|{
+                let storage = storage.read();
+                if valid(storage.as_ref(), entity.index){ Some((*id, storage.component_name())) } else { None }
+            })
+            .collect();
+        found.sort_by_key(|(_, name)| *name);
+        found
+    }
+
+    /// Whether the entity is alive and has a `T`
+    pub fn has<T: 'static>(&self, entity: &Entity) -> bool{
+        self.inspectable(entity) && self.storage(&TypeId::of::<T>())
+            .is_some_and(|storage| valid(storage.read().as_ref(), entity.index))
+    }
+
+    /// Type names of the components the entity has, in order
+    pub fn entity_components(&self, entity: &Entity) -> Vec<&'static str>{
+        self.storages_of(entity).into_iter().map(|(_, name)| name).collect()
+    }
+
+    /// Looks through every storage for the entity's components and formats them with the
+    /// `Debug` registered in the `TypeRegistry` resource
+    pub fn inspect(&self, entity: &Entity) -> EntityInspection{
+        let found = self.storages_of(entity);
+        let registry = if self.contains::<TypeRegistry>() { Some(self.get::<TypeRegistry>()) } else { None };
+        let components = found.into_iter()
+            .map(|(id, type_name)|{
+                let registration = registry.as_ref().and_then(|registry| registry.component_by_id(id));
+                InspectedComponent{
+                    type_name,
+                    registered: registration.map(|registration| registration.name().to_string()),
+                    value: registration.and_then(|registration|
+                        registration.debug(self.storage(&id).unwrap().read().as_ref(), entity.index)),
+                }
+            })
+            .collect();
+
+        EntityInspection{
+            entity: *entity,
+            alive: self.inspectable(entity),
+            components,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Position(f32, f32);
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Health(u32);
+
+    #[derive(Clone, Copy, PartialEq)]
+    struct Opaque;
+
+    fn world() -> World{
+        let mut world = World::new();
+        world.insert(EntityStorage::new());
+        world.register_comp::<Position>();
+        world.register_comp::<Health>();
+        world.register_comp::<Opaque>();
+        world.register_comp::<u8>();
+        let mut registry = TypeRegistry::new();
+        registry.register_comp::<Position>("position").with_debug();
+        registry.register_comp::<Opaque>("opaque");
+        world.insert(registry);
+        world
+    }
+
+    #[test]
+    fn inspects_components(){
+        let world = world();
+        let entity = *world.get_mut::<EntityStorage>().create_entity();
+        world.get_comp_mut::<Position>().set(&entity.index, Position(1.0, 2.0));
+        world.get_comp_mut::<Health>().set(&entity.index, Health(3));
+        world.get_comp_mut::<Opaque>().set(&entity.index, Opaque);
+
+        assert!(world.has::<Position>(&entity));
+        assert!(!world.has::<u8>(&entity));
+        assert!(!world.has::<u64>(&entity));
+        let mut expected = vec![std::any::type_name::<Position>(), std::any::type_name::<Health>(), std::any::type_name::<Opaque>()];
+        expected.sort();
+        assert_eq!(world.entity_components(&entity), expected);
+
+        let inspection = world.inspect(&entity);
+        assert!(inspection.alive);
+        let position = inspection.components.iter().find(|component| component.name() == "position").unwrap();
+        assert_eq!(position.value.as_deref(), Some("Position(1.0, 2.0)"));
+        let health = inspection.components.iter().find(|component| component.type_name == std::any::type_name::<Health>()).unwrap();
+        assert_eq!((health.registered.as_deref(), health.value.as_deref()), (None, None));
+
+        let text = inspection.to_string();
+        assert!(text.starts_with("entity 0v0: 3 components"));
+        assert!(text.contains("  position: Position(1.0, 2.0)"));
+        assert!(text.contains(&format!("  opaque: {}", NO_DEBUG)));
+        assert!(text.contains(&format!("  {}: {}", std::any::type_name::<Health>(), NO_DEBUG)));
+    }
+
+    #[test]
+    fn stale_handles_have_nothing(){
+        let world = world();
+        let entity = *world.get_mut::<EntityStorage>().create_entity();
+        world.get_comp_mut::<Health>().set(&entity.index, Health(1));
+        world.despawn(&entity);
+        let reused = *world.get_mut::<EntityStorage>().create_entity();
+        world.get_comp_mut::<Health>().set(&reused.index, Health(2));
+
+        assert_eq!(reused.index, entity.index);
+        assert!(!world.has::<Health>(&entity));
+        assert!(world.entity_components(&entity).is_empty());
+        let inspection = world.inspect(&entity);
+        assert!(!inspection.alive && inspection.components.is_empty());
+        assert_eq!(inspection.to_string(), "entity 0v0 (dead)\n");
+        assert!(world.has::<Health>(&reused));
+    }
+}
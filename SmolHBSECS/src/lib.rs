@@ -1,19 +1,54 @@
 pub mod component;
 pub mod world;
 pub mod system;
+pub mod command;
+pub mod stage;
+pub mod registry;
+pub mod entity_map;
+pub mod snapshot;
+pub mod diff;
+pub mod hierarchy;
+pub mod transform;
+pub mod transfer;
+pub mod stats;
+pub mod reflect;
+pub mod inspect;
+pub mod compact;
+pub mod reorder;
+#[cfg(feature = "serde")]
+pub mod prefab;
+#[cfg(feature = "serde")]
+pub mod scene;
+#[cfg(feature = "serde")]
+pub mod serialize;
 
 use SmolCommon::entity::*;
 use SmolCommon::component::*;
 use SmolCommon::system::WriteComp;
 use SmolCommon::join::{JoinIter, Joinable};
+use bit_vec::BitVec;
 use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
 
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entity{
     index: usize,
     generation: usize,
 }
 
+impl Entity{
+    /// Index of the entity's components in every storage
+    pub fn index(&self) -> usize{
+        self.index
+    }
+
+    /// How many times the index has been reused, stale handles have an older generation
+    pub fn generation(&self) -> usize{
+        self.generation
+    }
+}
+
 impl EntityCommon for Entity{
 
     fn add<'e, 'd: 'e, T: Component>(&'e self, storage: &'e mut WriteComp<'d, T>, comp: T) -> &'e Self{
@@ -34,9 +69,54 @@ impl PartialEq for Entity {
 }
 impl Eq for Entity {}
 
+impl Hash for Entity{
+    fn hash<H: Hasher>(&self, state: &mut H){
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "SavedEntities", into = "SavedEntities"))]
 pub struct EntityStorage{
     entities: Vec<Entity>,
     empties: VecDeque<Entity>,
+    // One bit per slot, set while the slot holds a live entity, so liveness doesn't have to search empties
+    alive: BitVec,
+}
+
+// What gets saved of an EntityStorage, the live bits are rebuilt from the empties when it's loaded
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedEntities{
+    entities: Vec<Entity>,
+    empties: VecDeque<Entity>,
+}
+
+#[cfg(feature = "serde")]
+impl From<SavedEntities> for EntityStorage{
+    fn from(saved: SavedEntities) -> Self{
+        let mut alive = BitVec::from_elem(saved.entities.len(), true);
+        for entity in saved.empties.iter(){
+            alive.set(entity.index, false);
+        }
+        EntityStorage{
+            entities: saved.entities,
+            empties: saved.empties,
+            alive,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<EntityStorage> for SavedEntities{
+    fn from(storage: EntityStorage) -> Self{
+        SavedEntities{
+            entities: storage.entities,
+            empties: storage.empties,
+        }
+    }
 }
 
 impl EntityStorage{
@@ -44,24 +124,82 @@ impl EntityStorage{
         EntityStorage{
             entities: Vec::new(),
             empties: VecDeque::new(),
+            alive: BitVec::new(),
         }
     }
 
     pub fn create_entity(&mut self) -> &Entity{
         match self.empties.pop_front(){
             Some(entity) => {
+                self.alive.set(entity.index, true);
                 self.entities.get(entity.index).unwrap()
             },
             None => {
                 self.entities.push(Entity{index: self.entities.len(), generation: 0});
+                self.alive.push(true);
                 &self.entities[self.entities.len()-1]
             },
         }
     }
 
     pub fn delete_entity(&mut self, entity: &Entity){
+        if !self.is_alive(entity){
+            return;
+        }
         self.entities[entity.index].generation += 1;
-        self.empties.push_back(entity.clone());
+        self.alive.set(entity.index, false);
+        self.empties.push_back(self.entities[entity.index]);
+    }
+
+    /// Whether the entity exists and the handle isn't stale
+    pub fn is_alive(&self, entity: &Entity) -> bool{
+        match self.entities.get(entity.index){
+            Some(current) => current == entity && self.alive[entity.index],
+            None => false,
+        }
+    }
+
+    /// Every live entity, in index order
+    pub fn live_entities(&self) -> Vec<Entity>{
+        self.join().copied().collect()
+    }
+
+    /// Moves live entities to new indices, returning the new handles in the same order.
+    /// Every live entity has to be moved and no two can go to the same index. Entities that change index
+    /// get a generation no handle to that index ever had, so stale handles stay stale.
+    pub(crate) fn renumber(&mut self, moves: &[(Entity, usize)]) -> Vec<Entity>{
+        let live = self.live_entities();
+        assert!(moves.len() == live.len() && moves.iter().all(|(entity, _)| self.is_alive(entity)), "Every live entity has to be renumbered");
+
+        let len = moves.iter().map(|(_, to)| to + 1).max().unwrap_or(0).max(self.entities.len());
+        let mut was_live = vec![false; len];
+        for entity in live.iter(){
+            was_live[entity.index] = true;
+        }
+        // Slots that held a live entity bump their generation, dead ones already did when they were deleted
+        let mut entities: Vec<Entity> = (0..len)
+            .map(|index| match self.entities.get(index){
+                Some(entity) => Entity{index, generation: entity.generation + was_live[index] as usize},
+                None => Entity{index, generation: 0},
+            })
+            .collect();
+
+        let mut taken = vec![false; len];
+        let renumbered = moves.iter()
+            .map(|(entity, to)|{
+                assert!(!taken[*to], "Two entities renumbered to index {}", to);
+                taken[*to] = true;
+                if entity.index == *to{
+                    entities[*to] = *entity;
+                }
+                entities[*to]
+            })
+            .collect();
+
+        self.empties = entities.iter().filter(|entity| !taken[entity.index]).copied().collect();
+        self.alive = taken.into_iter().collect();
+        self.entities = entities;
+        renumbered
     }
 }
 
@@ -71,9 +209,43 @@ impl<'j> Joinable<'j> for &'j EntityStorage{
     fn join(self) -> JoinIter<'j, Self::Target>{
         JoinIter{
             items: Box::new(
-                self.entities.iter().filter_map(move |entity|{
-                    Some((!self.empties.contains(entity), Some(entity)))
-                })),
+                self.entities.iter().zip(self.alive.iter()).map(|(entity, alive)| (alive, Some(entity)))),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn deleted_entities_are_dead(){
+        let mut storage = EntityStorage::new();
+        let entities: Vec<Entity> = (0..3).map(|_| *storage.create_entity()).collect();
+
+        // The slot's live bit goes, so joins skip it and the old handle reads as dead
+        storage.delete_entity(&entities[1]);
+        assert!(!storage.is_alive(&entities[1]));
+        assert_eq!(storage.live_entities(), vec![entities[0], entities[2]]);
+
+        // Deleting a dead handle doesn't free the slot a second time
+        storage.delete_entity(&entities[1]);
+        let reused = *storage.create_entity();
+        assert_eq!((reused.index, reused.generation), (1, 1));
+        assert_eq!(storage.create_entity().index, 3);
+        assert!(!storage.is_alive(&entities[1]) && storage.is_alive(&reused));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn loaded_storage_knows_the_dead(){
+        let mut storage = EntityStorage::new();
+        let entities: Vec<Entity> = (0..3).map(|_| *storage.create_entity()).collect();
+        storage.delete_entity(&entities[0]);
+
+        let mut loaded: EntityStorage = serde_json::from_str(&serde_json::to_string(&storage).unwrap()).unwrap();
+        assert_eq!(loaded.live_entities(), vec![entities[1], entities[2]]);
+        assert!(!loaded.is_alive(&entities[0]));
+        assert_eq!(*loaded.create_entity(), Entity{index: 0, generation: 1});
+    }
+}
@@ -0,0 +1,293 @@
+use crate::world::World;
+use crate::{Entity, EntityStorage};
+use crate::command::Commands;
+use crate::registry::TypeRegistry;
+use crate::serialize::{SerializeError, component_serde};
+use SmolCommon::WorldCommon;
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// A named entity template, components are keyed by their registered names.
+/// Components of the prefab this inherits from are merged in field by field,
+/// so an override only has to list the fields it changes.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Prefab{
+    #[serde(default)]
+    pub inherits: Option<String>,
+    #[serde(default)]
+    pub components: BTreeMap<String, Value>,
+}
+
+// RON struct syntax only parses into RON's own values, they're converted after
+#[derive(Deserialize)]
+struct RonPrefab{
+    #[serde(default)]
+    inherits: Option<String>,
+    #[serde(default)]
+    components: BTreeMap<String, ron::Value>,
+}
+
+/// Every loaded prefab by name, spawning from the world reads this and the `TypeRegistry` as resources
+#[derive(Default)]
+pub struct Prefabs{
+    prefabs: HashMap<String, Prefab>,
+    // Prefabs queued with `Commands::spawn_prefab` that couldn't be spawned, with why
+    errors: Vec<(String, SerializeError)>,
+}
+
+// Objects are merged key by key, anything else is replaced
+fn merge(base: &mut Value, over: Value){
+    match (base, over){
+        (Value::Object(base), Value::Object(over)) => {
+            for (key, value) in over{
+                match base.get_mut(&key){
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    },
+                }
+            }
+        },
+        (base, over) => *base = over,
+    }
+}
+
+impl Prefabs{
+    pub fn new() -> Self{
+        Prefabs::default()
+    }
+
+    /// Adds or replaces a prefab
+    pub fn insert(&mut self, name: &str, prefab: Prefab){
+        self.prefabs.insert(name.to_string(), prefab);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Prefab>{
+        self.prefabs.get(name)
+    }
+
+    pub fn len(&self) -> usize{
+        self.prefabs.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.prefabs.is_empty()
+    }
+
+    /// Prefabs queued with `Commands::spawn_prefab` that failed to spawn, by name
+    pub fn errors(&self) -> &[(String, SerializeError)]{
+        &self.errors
+    }
+
+    /// Takes the spawn errors since the last call
+    pub fn drain_errors(&mut self) -> Vec<(String, SerializeError)>{
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Loads a JSON object of prefabs by name, replacing prefabs with the same names
+    pub fn load_json(&mut self, data: &str) -> Result<(), SerializeError>{
+        let prefabs: BTreeMap<String, Prefab> = serde_json::from_str(data)?;
+        self.prefabs.extend(prefabs);
+        Ok(())
+    }
+
+    /// Loads a RON map of prefabs by name, replacing prefabs with the same names
+    pub fn load_ron(&mut self, data: &str) -> Result<(), SerializeError>{
+        let prefabs: BTreeMap<String, RonPrefab> = ron::from_str(data)?;
+        for (name, prefab) in prefabs{
+            let components = prefab.components.into_iter()
+                .map(|(component, value)| serde_json::to_value(value).map(|value| (component, value)))
+                .collect::<Result<_, _>>()?;
+            self.insert(&name, Prefab{
+                inherits: prefab.inherits,
+                components,
+            });
+        }
+        Ok(())
+    }
+
+    /// The components of a prefab with everything it inherits merged in
+    pub fn resolve(&self, name: &str) -> Result<BTreeMap<String, Value>, SerializeError>{
+        let mut chain = Vec::new();
+        let mut next = Some(name);
+        while let Some(name) = next{
+            let prefab = self.get(name).ok_or_else(|| SerializeError::UnknownPrefab(name.to_string()))?;
+            if chain.iter().any(|(n, _)| *n == name){
+                return Err(SerializeError::PrefabCycle(name.to_string()));
+            }
+            chain.push((name, prefab));
+            next = prefab.inherits.as_deref();
+        }
+
+        let mut components = BTreeMap::new();
+        for (_, prefab) in chain.into_iter().rev(){
+            for (component, value) in prefab.components.iter(){
+                match components.get_mut(component){
+                    Some(existing) => merge(existing, value.clone()),
+                    None => {
+                        components.insert(component.clone(), value.clone());
+                    },
+                }
+            }
+        }
+        Ok(components)
+    }
+
+    /// Creates an entity with the prefab's components, the components need `with_serde`
+    pub fn spawn(&self, world: &World, registry: &TypeRegistry, name: &str) -> Result<Entity, SerializeError>{
+        let components = self.resolve(name)?;
+        // Look everything up before creating the entity, a value that doesn't deserialize deletes it again below
+        let components = components.into_iter()
+            .map(|(component, value)| component_serde(registry, &component).map(|serde| (serde, value)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let entity = *world.get_mut::<EntityStorage>().create_entity();
+        let added = components.into_iter().try_for_each(|(serde, value)| (serde.add_json)(world, &entity, value));
+        if let Err(e) = added{
+            // Takes the components that were already added with it
+            world.delete_entities(&[entity]);
+            return Err(e.into());
+        }
+        Ok(entity)
+    }
+}
+
+impl World{
+    /// Creates an entity from a prefab in the world's `Prefabs` resource, using the world's `TypeRegistry`
+    pub fn spawn_prefab(&self, name: &str) -> Result<Entity, SerializeError>{
+        let prefabs = self.get::<Prefabs>();
+        let registry = self.get::<TypeRegistry>();
+        prefabs.spawn(self, &registry, name)
+    }
+}
+
+impl Commands{
+    /// Queues spawning a prefab. If it can't be spawned when the commands are applied,
+    /// the error goes to `Prefabs::errors` and nothing is spawned.
+    pub fn spawn_prefab(&mut self, name: &str){
+        let name = name.to_string();
+        self.push(move |world|{
+            if let Err(e) = world.spawn_prefab(&name){
+                world.get_mut::<Prefabs>().errors.push((name, e));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    struct Position{
+        x: f32,
+        y: f32,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    struct Radius(f32);
+
+    fn world() -> World{
+        let mut registry = TypeRegistry::new();
+        registry.register_comp::<Position>("Position").with_serde();
+        registry.register_comp::<Radius>("Radius").with_serde();
+
+        let mut world = World::new();
+        world.insert(EntityStorage::new());
+        registry.register_all(&mut world);
+        world.insert(registry);
+        world.insert(Prefabs::new());
+        world
+    }
+
+    #[test]
+    fn spawn_with_overrides(){
+        let world = world();
+        world.get_mut::<Prefabs>().load_json(r#"{
+            "Ball": {"components": {"Position": {"x": 1.0, "y": 2.0}, "Radius": 0.5}},
+            "BigBall": {"inherits": "Ball", "components": {"Position": {"y": 7.0}, "Radius": 3.0}}
+        }"#).unwrap();
+
+        let ball = world.spawn_prefab("Ball").unwrap();
+        let big = world.spawn_prefab("BigBall").unwrap();
+
+        assert_eq!(*world.get_comp::<Position>().get(&ball.index).unwrap(), Position{x: 1.0, y: 2.0});
+        assert_eq!(*world.get_comp::<Position>().get(&big.index).unwrap(), Position{x: 1.0, y: 7.0});
+        assert_eq!(*world.get_comp::<Radius>().get(&big.index).unwrap(), Radius(3.0));
+    }
+
+    #[test]
+    fn load_ron(){
+        let world = world();
+        world.get_mut::<Prefabs>().load_ron(r#"{
+            "Planet": (components: {"Position": (x: 3.0, y: 4.0), "Radius": 10.0}),
+        }"#).unwrap();
+
+        let planet = world.spawn_prefab("Planet").unwrap();
+        assert_eq!(*world.get_comp::<Position>().get(&planet.index).unwrap(), Position{x: 3.0, y: 4.0});
+        assert_eq!(*world.get_comp::<Radius>().get(&planet.index).unwrap(), Radius(10.0));
+    }
+
+    #[test]
+    fn bad_prefabs(){
+        let world = world();
+        let mut prefabs = world.get_mut::<Prefabs>();
+        prefabs.insert("A", Prefab{inherits: Some("B".to_string()), components: BTreeMap::new()});
+        prefabs.insert("B", Prefab{inherits: Some("A".to_string()), components: BTreeMap::new()});
+        prefabs.insert("Orphan", Prefab{inherits: Some("Missing".to_string()), components: BTreeMap::new()});
+        let mut unknown = Prefab::default();
+        unknown.components.insert("Velocity".to_string(), Value::Null);
+        prefabs.insert("Unknown", unknown);
+
+        let registry = world.get::<TypeRegistry>();
+        assert!(matches!(prefabs.spawn(&world, &registry, "A"), Err(SerializeError::PrefabCycle(_))));
+        assert!(matches!(prefabs.spawn(&world, &registry, "Orphan"), Err(SerializeError::UnknownPrefab(_))));
+        assert!(matches!(prefabs.spawn(&world, &registry, "Unknown"), Err(SerializeError::UnknownComponent(_))));
+        assert!(world.get::<EntityStorage>().live_entities().is_empty());
+    }
+
+    #[test]
+    fn malformed_value_spawns_nothing(){
+        let world = world();
+        world.get_mut::<Prefabs>().load_json(r#"{
+            "Ball": {"components": {"Position": {"x": 1.0, "y": 2.0}, "Radius": 0.5}},
+            "Broken": {"inherits": "Ball", "components": {"Radius": "big"}}
+        }"#).unwrap();
+        world.spawn_prefab("Ball").unwrap();
+
+        // Position comes first and is already added when Radius fails
+        assert!(world.spawn_prefab("Broken").is_err());
+        assert_eq!(world.get::<EntityStorage>().live_entities().len(), 1);
+        assert_eq!(world.get_comp::<Position>().iter().filter(|(valid, _)| *valid).count(), 1);
+        assert_eq!(world.spawn_prefab("Ball").unwrap().index, 1);
+    }
+
+    #[test]
+    fn spawn_from_commands(){
+        let mut world = world();
+        world.insert(Commands::new());
+        let mut prefab = Prefab::default();
+        prefab.components.insert("Radius".to_string(), serde_json::json!(2.0));
+        world.get_mut::<Prefabs>().insert("Dot", prefab);
+
+        world.get_mut::<Commands>().spawn_prefab("Dot");
+        world.get_mut::<Commands>().spawn_prefab("Dot");
+        assert!(world.get::<EntityStorage>().live_entities().is_empty());
+
+        Commands::apply(&world);
+        assert_eq!(world.get::<EntityStorage>().live_entities().len(), 2);
+        assert_eq!(*world.get_comp::<Radius>().get(&1).unwrap(), Radius(2.0));
+
+        // Bad data doesn't stop the other commands, the error is kept for whoever wants to report it
+        world.get_mut::<Commands>().spawn_prefab("Missing");
+        world.get_mut::<Commands>().spawn_prefab("Dot");
+        Commands::apply(&world);
+        assert_eq!(world.get::<EntityStorage>().live_entities().len(), 3);
+        let errors = world.get_mut::<Prefabs>().drain_errors();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].0 == "Missing" && matches!(errors[0].1, SerializeError::UnknownPrefab(_)));
+        assert!(world.get::<Prefabs>().errors().is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::world::World;
+use std::any::TypeId;
+use std::fmt;
+
+/// How a storage lays out its components
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageKind{
+    /// `VecStorage`, one slot per entity index up to the highest one set
+    Vec,
+}
+
+/// Bytes a storage takes, estimated from its length and capacity
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage{
+    /// Bytes of the slots up to the highest index set
+    pub used: usize,
+    /// Bytes allocated, this only shrinks when `World::compact` runs
+    pub reserved: usize,
+}
+
+impl MemoryUsage{
+    /// Allocated bytes past the end of the used slots
+    pub fn spare(&self) -> usize{
+        self.reserved - self.used
+    }
+}
+
+impl std::ops::Add for MemoryUsage{
+    type Output = MemoryUsage;
+
+    fn add(self, other: MemoryUsage) -> MemoryUsage{
+        MemoryUsage{
+            used: self.used + other.used,
+            reserved: self.reserved + other.reserved,
+        }
+    }
+}
+
+/// A registered component storage, from `World::components`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComponentInfo{
+    pub id: TypeId,
+    pub name: &'static str,
+    pub size: usize,
+    pub align: usize,
+    pub storage: StorageKind,
+    /// Entities that have the component
+    pub live: usize,
+    pub memory: MemoryUsage,
+}
+
+/// An inserted resource, from `World::resources`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceInfo{
+    pub id: TypeId,
+    pub name: &'static str,
+    pub size: usize,
+    pub align: usize,
+}
+
+impl fmt::Display for ComponentInfo{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        write!(f, "{} ({} bytes, align {}, {:?} storage): {} live, {} bytes used, {} reserved",
+            self.name, self.size, self.align, self.storage, self.live, self.memory.used, self.memory.reserved)
+    }
+}
+
+impl fmt::Display for ResourceInfo{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        write!(f, "{} ({} bytes, align {})", self.name, self.size, self.align)
+    }
+}
+
+impl World{
+    /// Every registered component storage, in type name order
+    pub fn components(&self) -> impl Iterator<Item = ComponentInfo>{
+        let mut components: Vec<ComponentInfo> = self.storages()
+            .map(|(id, storage)|{
+                let storage = storage.read();
+                let layout = storage.layout();
+                ComponentInfo{
+                    id: *id,
+                    name: storage.component_name(),
+                    size: layout.size(),
+                    align: layout.align(),
+                    storage: storage.kind(),
+                    live: storage.live(),
+                    memory: storage.memory(),
+                }
+            })
+            .collect();
+        components.sort_by_key(|info| info.name);
+        components.into_iter()
+    }
+
+    pub fn component_info<T: 'static>(&self) -> Option<ComponentInfo>{
+        self.components().find(|info| info.id == TypeId::of::<T>())
+    }
+
+    /// Every resource in the world, in type name order
+    pub fn resources(&self) -> impl Iterator<Item = ResourceInfo>{
+        let mut resources: Vec<ResourceInfo> = self.resource_entries()
+            .map(|(id, name, layout)| ResourceInfo{
+                id: *id,
+                name,
+                size: layout.size(),
+                align: layout.align(),
+            })
+            .collect();
+        resources.sort_by_key(|info| info.name);
+        resources.into_iter()
+    }
+
+    /// Memory of every component storage together
+    pub fn storage_memory(&self) -> MemoryUsage{
+        self.components().fold(MemoryUsage::default(), |total, info| total + info.memory)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::EntityStorage;
+    use SmolCommon::WorldCommon;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Position([f32; 3]);
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    #[repr(align(16))]
+    struct Aligned(u8);
+
+    #[test]
+    fn lists_types(){
+        let mut world = World::new();
+        world.insert(EntityStorage::new());
+        world.insert(7u64);
+        world.register_comp::<Position>();
+        world.register_comp::<Aligned>();
+        for index in 0..10{
+            world.get_comp_mut::<Position>().set(&index, Position([0.0; 3]));
+        }
+        world.get_comp_mut::<Position>().delete(&3);
+        world.get_comp_mut::<Aligned>().set(&2, Aligned(1));
+
+        let position = world.component_info::<Position>().unwrap();
+        assert_eq!(position.name, std::any::type_name::<Position>());
+        assert_eq!((position.size, position.align, position.storage, position.live), (12, 4, StorageKind::Vec, 9));
+        assert!(position.memory.used >= 10 * 12);
+        assert!(position.memory.reserved >= position.memory.used);
+
+        let aligned = world.component_info::<Aligned>().unwrap();
+        assert_eq!((aligned.size, aligned.align, aligned.live), (16, 16, 1));
+        assert!(world.component_info::<u32>().is_none());
+        assert_eq!(world.components().count(), 2);
+        assert_eq!(world.storage_memory().used, position.memory.used + aligned.memory.used);
+
+        let resources: Vec<ResourceInfo> = world.resources().collect();
+        assert_eq!(resources.len(), 2);
+        let number = resources.iter().find(|info| info.id == TypeId::of::<u64>()).unwrap();
+        assert_eq!((number.name, number.size, number.align), ("u64", 8, 8));
+        assert_eq!(number.to_string(), "u64 (8 bytes, align 8)");
+
+        world.remove::<u64>();
+        assert_eq!(world.resources().count(), 1);
+    }
+
+    #[test]
+    fn storages_keep_memory(){
+        let mut world = World::new();
+        world.register_comp::<u64>();
+        for index in 0..1000{
+            world.get_comp_mut::<u64>().set(&index, 1);
+        }
+        for index in 0..1000{
+            world.get_comp_mut::<u64>().delete(&index);
+        }
+        // Nothing's live but the slots are still there, which is what shows a storage that needs trimming
+        let info = world.component_info::<u64>().unwrap();
+        assert_eq!(info.live, 0);
+        assert!(info.memory.used >= 1000 * 8);
+    }
+}
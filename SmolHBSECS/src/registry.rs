@@ -0,0 +1,322 @@
+use crate::world::World;
+use crate::entity_map::{EntityMap, MapEntities};
+use crate::component::{AnyStorage, VecStorage};
+use SmolCommon::WorldCommon;
+use SmolCommon::component::{Component, ComponentStorage};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+#[cfg(feature = "serde")]
+use crate::serialize::{ComponentSerde, ResourceSerde};
+
+// Format the component at an index, and compare the components at the same index of two storages
+type DebugFn = fn(&dyn AnyStorage, usize) -> Option<String>;
+type EqFn = fn(&dyn AnyStorage, &dyn AnyStorage, usize) -> bool;
+
+/// Maps stable names to component and resource types.
+/// Type ids and type names can change between builds, these names are what gets written to saved data.
+#[derive(Default)]
+pub struct TypeRegistry{
+    components: Vec<ComponentRegistration>,
+    component_names: HashMap<String, usize>,
+    component_ids: HashMap<TypeId, usize>,
+    resources: Vec<ResourceRegistration>,
+    resource_names: HashMap<String, usize>,
+    resource_ids: HashMap<TypeId, usize>,
+}
+
+pub struct ComponentRegistration{
+    name: String,
+    type_id: TypeId,
+    type_name: &'static str,
+    register: fn(&mut World),
+    map_entities: Option<fn(&World, &EntityMap, &[usize])>,
+    debug: Option<DebugFn>,
+    eq: Option<EqFn>,
+    #[cfg(feature = "serde")]
+    pub(crate) serde: Option<ComponentSerde>,
+}
+
+impl ComponentRegistration{
+    pub fn name(&self) -> &str{
+        &self.name
+    }
+
+    pub fn type_id(&self) -> TypeId{
+        self.type_id
+    }
+
+    pub fn type_name(&self) -> &'static str{
+        self.type_name
+    }
+
+    /// Registers the component's storage in the world if it isn't there yet
+    pub fn register(&self, world: &mut World){
+        (self.register)(world)
+    }
+
+    /// Rewrites the entity references in this component at the given indices, does nothing if it didn't opt in
+    pub fn map_entities(&self, world: &World, map: &EntityMap, indices: &[usize]){
+        if let Some(map_entities) = self.map_entities{
+            map_entities(world, map, indices);
+        }
+    }
+
+    /// Formats the component at the index of a storage of this type, None if it didn't opt into `with_debug`
+    pub fn debug(&self, storage: &dyn AnyStorage, index: usize) -> Option<String>{
+        self.debug.and_then(|debug| debug(storage, index))
+    }
+
+    /// Compares the components at the same index of two storages of this type, None if it didn't opt into `with_eq`
+    pub fn eq(&self, a: &dyn AnyStorage, b: &dyn AnyStorage, index: usize) -> Option<bool>{
+        self.eq.map(|eq| eq(a, b, index))
+    }
+}
+
+pub struct ResourceRegistration{
+    name: String,
+    type_id: TypeId,
+    type_name: &'static str,
+    #[cfg(feature = "serde")]
+    pub(crate) serde: Option<ResourceSerde>,
+}
+
+impl ResourceRegistration{
+    pub fn name(&self) -> &str{
+        &self.name
+    }
+
+    pub fn type_id(&self) -> TypeId{
+        self.type_id
+    }
+
+    pub fn type_name(&self) -> &'static str{
+        self.type_name
+    }
+}
+
+/// Returned when registering a component, used to opt the component into extra features
+pub struct ComponentBuilder<'r, T>{
+    pub(crate) registration: &'r mut ComponentRegistration,
+    marker: PhantomData<T>,
+}
+
+/// Returned when registering a resource, used to opt the resource into extra features
+pub struct ResourceBuilder<'r, T>{
+    // Only `with_serde` reads it so far
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub(crate) registration: &'r mut ResourceRegistration,
+    marker: PhantomData<T>,
+}
+
+fn register_storage<T: 'static + Component>(world: &mut World){
+    if !world.contains_comp::<T>(){
+        world.register_comp::<T>();
+    }
+}
+
+fn map_storage<T: 'static + Component + MapEntities>(world: &World, map: &EntityMap, indices: &[usize]){
+    if !world.contains_comp::<T>(){
+        return;
+    }
+    let mut storage = world.get_comp_mut::<T>();
+    for index in indices{
+        if let Some(comp) = storage.get_mut(index){
+            comp.map_entities(map);
+        }
+    }
+}
+
+fn debug_entry<T: 'static + Component + Debug>(storage: &dyn AnyStorage, index: usize) -> Option<String>{
+    let storage = storage.as_any().downcast_ref::<VecStorage<T>>().unwrap();
+    storage.get(&index).map(|comp| format!("{:?}", comp))
+}
+
+fn eq_entry<T: 'static + Component + PartialEq>(a: &dyn AnyStorage, b: &dyn AnyStorage, index: usize) -> bool{
+    let a = a.as_any().downcast_ref::<VecStorage<T>>().unwrap();
+    let b = b.as_any().downcast_ref::<VecStorage<T>>().unwrap();
+    a.get(&index) == b.get(&index)
+}
+
+impl<'r, T> ComponentBuilder<'r, T>
+    where T: 'static + Component + Debug{
+    /// Lets diffs and inspection print this component's values
+    pub fn with_debug(self) -> Self{
+        self.registration.debug = Some(debug_entry::<T>);
+        self
+    }
+}
+
+impl<'r, T> ComponentBuilder<'r, T>
+    where T: 'static + Component + PartialEq{
+    /// Lets diffs tell when this component's value changed
+    pub fn with_eq(self) -> Self{
+        self.registration.eq = Some(eq_entry::<T>);
+        self
+    }
+}
+
+impl<'r, T> ComponentBuilder<'r, T>
+    where T: 'static + Component + MapEntities{
+    /// Rewrites the entities stored in this component when entities get renumbered
+    pub fn with_map_entities(self) -> Self{
+        self.registration.map_entities = Some(map_storage::<T>);
+        self
+    }
+}
+
+impl TypeRegistry{
+    pub fn new() -> Self{
+        TypeRegistry::default()
+    }
+
+    /// Registers a component under a name, registering the same type again replaces its name
+    pub fn register_comp<T: 'static + Component>(&mut self, name: &str) -> ComponentBuilder<'_, T>{
+        let type_id = TypeId::of::<T>();
+        if let Some(&index) = self.component_names.get(name){
+            assert!(self.components[index].type_id == type_id, "Component name {} is already registered to another type", name);
+        }
+
+        let registration = ComponentRegistration{
+            name: name.to_string(),
+            type_id,
+            type_name: std::any::type_name::<T>(),
+            register: register_storage::<T>,
+            map_entities: None,
+            debug: None,
+            eq: None,
+            #[cfg(feature = "serde")]
+            serde: None,
+        };
+
+        let index = match self.component_ids.get(&type_id){
+            Some(&index) => {
+                self.component_names.remove(&self.components[index].name);
+                self.components[index] = registration;
+                index
+            },
+            None => {
+                self.components.push(registration);
+                self.components.len() - 1
+            },
+        };
+        self.component_names.insert(name.to_string(), index);
+        self.component_ids.insert(type_id, index);
+
+        ComponentBuilder{
+            registration: &mut self.components[index],
+            marker: PhantomData,
+        }
+    }
+
+    /// Registers a resource under a name, registering the same type again replaces its name
+    pub fn register_res<T: 'static + Any>(&mut self, name: &str) -> ResourceBuilder<'_, T>{
+        let type_id = TypeId::of::<T>();
+        if let Some(&index) = self.resource_names.get(name){
+            assert!(self.resources[index].type_id == type_id, "Resource name {} is already registered to another type", name);
+        }
+
+        let registration = ResourceRegistration{
+            name: name.to_string(),
+            type_id,
+            type_name: std::any::type_name::<T>(),
+            #[cfg(feature = "serde")]
+            serde: None,
+        };
+
+        let index = match self.resource_ids.get(&type_id){
+            Some(&index) => {
+                self.resource_names.remove(&self.resources[index].name);
+                self.resources[index] = registration;
+                index
+            },
+            None => {
+                self.resources.push(registration);
+                self.resources.len() - 1
+            },
+        };
+        self.resource_names.insert(name.to_string(), index);
+        self.resource_ids.insert(type_id, index);
+
+        ResourceBuilder{
+            registration: &mut self.resources[index],
+            marker: PhantomData,
+        }
+    }
+
+    pub fn component(&self, name: &str) -> Option<&ComponentRegistration>{
+        self.component_names.get(name).map(|&index| &self.components[index])
+    }
+
+    pub fn component_of<T: Any>(&self) -> Option<&ComponentRegistration>{
+        self.component_by_id(TypeId::of::<T>())
+    }
+
+    pub fn component_by_id(&self, type_id: TypeId) -> Option<&ComponentRegistration>{
+        self.component_ids.get(&type_id).map(|&index| &self.components[index])
+    }
+
+    pub fn components(&self) -> impl Iterator<Item = &ComponentRegistration>{
+        self.components.iter()
+    }
+
+    pub fn resource(&self, name: &str) -> Option<&ResourceRegistration>{
+        self.resource_names.get(name).map(|&index| &self.resources[index])
+    }
+
+    pub fn resource_of<T: Any>(&self) -> Option<&ResourceRegistration>{
+        self.resource_ids.get(&TypeId::of::<T>()).map(|&index| &self.resources[index])
+    }
+
+    pub fn resources(&self) -> impl Iterator<Item = &ResourceRegistration>{
+        self.resources.iter()
+    }
+
+    /// Registers the storage of every registered component in the world
+    pub fn register_all(&self, world: &mut World){
+        for component in self.components.iter(){
+            component.register(world);
+        }
+    }
+
+    /// Rewrites entity references in every opted in component of the entities the map points to
+    pub fn map_entities(&self, world: &World, map: &EntityMap){
+        let indices: Vec<usize> = map.iter().map(|(_, to)| to.index()).collect();
+        for component in self.components.iter(){
+            component.map_entities(world, map, &indices);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[derive(Clone, Copy)]
+    struct Position;
+
+    #[test]
+    fn register_and_look_up(){
+        let mut registry = TypeRegistry::new();
+        registry.register_comp::<Position>("Position");
+        registry.register_comp::<u32>("Health");
+        registry.register_res::<String>("Title");
+
+        assert_eq!(registry.component("Position").unwrap().type_id(), TypeId::of::<Position>());
+        assert_eq!(registry.component_of::<u32>().unwrap().name(), "Health");
+        assert_eq!(registry.resource_of::<String>().unwrap().name(), "Title");
+        assert!(registry.component("Velocity").is_none());
+
+        // Renaming keeps one registration per type
+        registry.register_comp::<u32>("Hp");
+        assert!(registry.component("Health").is_none());
+        assert_eq!(registry.components().count(), 2);
+
+        let mut world = World::new();
+        registry.register_all(&mut world);
+        assert!(world.contains_comp::<Position>());
+        assert!(world.contains_comp::<u32>());
+    }
+}
@@ -0,0 +1,109 @@
+use crate::world::World;
+use crate::{Entity, EntityStorage};
+use crate::entity_map::EntityMap;
+use SmolCommon::WorldCommon;
+
+// Spreads the low 21 bits out to every third bit
+fn spread(value: u32) -> u64{
+    let mut value = value as u64 & 0x1f_ffff;
+    value = (value | value << 32) & 0x1f_0000_0000_ffff;
+    value = (value | value << 16) & 0x1f_0000_ff00_00ff;
+    value = (value | value << 8) & 0x100f_00f0_0f00_f00f;
+    value = (value | value << 4) & 0x10c3_0c30_c30c_30c3;
+    value = (value | value << 2) & 0x1249_2492_4924_9249;
+    value
+}
+
+/// Interleaves the low 21 bits of each coordinate, so points close together in space get codes close together.
+/// Quantize positions to cells first, sorting by this is the usual key for `World::reorder_by_key`.
+pub fn morton_code(x: u32, y: u32, z: u32) -> u64{
+    spread(x) | spread(y) << 1 | spread(z) << 2
+}
+
+impl World{
+    /// Permutes the live entities between the indices they have so iterating any storage visits them in `key` order,
+    /// entities with equal keys keep their order. Holes stay where they are, `defragment` first to close them.
+    /// Every storage moves with the entities. Handles to entities that moved go stale, the returned map has the
+    /// new handle of every live entity and also goes to the `RemapEvents` resource if there is one.
+    pub fn reorder_by_key<K: Ord, F: FnMut(&Entity) -> K>(&self, mut key: F) -> EntityMap{
+        let slots = self.get::<EntityStorage>().live_entities();
+        let mut keyed: Vec<(K, Entity)> = slots.iter().map(|entity| (key(entity), *entity)).collect();
+        // The key can hold storage guards, they have to go before the storages are written
+        drop(key);
+        keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let moves: Vec<(Entity, usize)> = keyed.into_iter()
+            .zip(slots.iter())
+            .map(|((_, entity), slot)| (entity, slot.index))
+            .collect();
+        self.renumber(&moves)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::entity_map::RemapEvents;
+    use crate::hierarchy::Parent;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Position([f32; 3]);
+
+    #[test]
+    fn morton_interleaves(){
+        assert_eq!(morton_code(0, 0, 0), 0);
+        assert_eq!(morton_code(1, 0, 0), 0b001);
+        assert_eq!(morton_code(0, 1, 0), 0b010);
+        assert_eq!(morton_code(0, 0, 1), 0b100);
+        assert_eq!(morton_code(3, 0, 1), 0b001_101);
+        assert_eq!(morton_code(0x1f_ffff, 0x1f_ffff, 0x1f_ffff), (1 << 63) - 1);
+    }
+
+    #[test]
+    fn reorders_in_lockstep(){
+        let mut world = World::new();
+        world.insert(EntityStorage::new());
+        world.insert(RemapEvents::new());
+        world.register_hierarchy();
+        world.register_comp::<Position>();
+        world.register_comp::<u32>();
+        let e: Vec<Entity> = (0..20u32).map(|n|{
+            let entity = *world.get_mut::<EntityStorage>().create_entity();
+            let cell = (n * 7) % 20;
+            world.get_comp_mut::<Position>().set(&entity.index, Position([(cell % 4) as f32, (cell / 4) as f32, 0.0]));
+            world.get_comp_mut::<u32>().set(&entity.index, n);
+            entity
+        }).collect();
+        world.set_parent(&e[1], &e[0]);
+        world.despawn(&e[3]);
+
+        let positions = world.get_comp::<Position>();
+        let key = |position: &Position| morton_code(position.0[0] as u32, position.0[1] as u32, position.0[2] as u32);
+        let before: Vec<(u32, u64)> = (0..20u32).filter(|n| *n != 3)
+            .map(|n| (n, key(positions.get(&e[n as usize].index).unwrap())))
+            .collect();
+        // Moving the guard in lets it go before the storages are written
+        let map = world.reorder_by_key(move |entity| key(positions.get(&entity.index).unwrap()));
+
+        // Iterating goes in key order, the hole stays and the other components came along
+        let keys: Vec<u64> = world.get_comp::<Position>().iter().filter_map(|(_, position)| position).map(key).collect();
+        assert!(keys.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(keys.len(), 19);
+        assert!(world.get_comp::<u32>().get(&3).is_none());
+        for (n, code) in before{
+            let moved = map.map(&e[n as usize]);
+            assert_ne!(moved.index, 3);
+            assert_eq!(*world.get_comp::<u32>().get(&moved.index).unwrap(), n);
+            assert_eq!(key(world.get_comp::<Position>().get(&moved.index).unwrap()), code);
+        }
+        assert_eq!(world.parent(&map.map(&e[1])), Some(map.map(&e[0])));
+        assert_eq!(world.get_comp::<Parent>().iter().filter(|(valid, _)| *valid).count(), 1);
+
+        // Handles kept from before can catch up through the events
+        let defragmented = world.defragment();
+        let events = world.get::<RemapEvents>();
+        assert_eq!(events.events().len(), 2);
+        assert_eq!(events.map(&e[7]), defragmented.map(&map.map(&e[7])));
+        assert!(world.get::<EntityStorage>().is_alive(&events.map(&e[7])));
+    }
+}
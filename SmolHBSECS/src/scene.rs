@@ -0,0 +1,325 @@
+use crate::world::World;
+use crate::{Entity, EntityStorage};
+use crate::entity_map::EntityMap;
+use crate::hierarchy::{Parent, Children};
+use crate::registry::TypeRegistry;
+use crate::serialize::SerializeError;
+use SmolCommon::WorldCommon;
+
+use serde::{Serialize, Deserialize};
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SceneId(pub u32);
+
+/// Which scene an entity was streamed in with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SceneMember(pub SceneId);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SceneEvent{
+    /// Every entity of the scene has its components
+    Loaded(SceneId),
+    /// Every entity of the scene was despawned
+    Unloaded(SceneId),
+}
+
+// A parsed chunk waiting to be copied into the world a few entities at a time
+struct SceneLoad{
+    scene: SceneId,
+    staging: World,
+    entities: Vec<Entity>,
+    map: Option<EntityMap>,
+    next: usize,
+}
+
+/// Streams scenes in and out of the world it's a resource of, call `World::update_scenes` once a frame.
+/// Loading spreads copying the components over frames, at most `budget` entities a frame.
+pub struct SceneManager{
+    budget: usize,
+    loading: VecDeque<SceneLoad>,
+    unloading: Vec<SceneId>,
+    events: Vec<SceneEvent>,
+}
+
+impl Default for SceneManager{
+    fn default() -> Self{
+        SceneManager::new(256)
+    }
+}
+
+impl SceneManager{
+    pub fn new(budget: usize) -> Self{
+        SceneManager{
+            budget: budget.max(1),
+            loading: VecDeque::new(),
+            unloading: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn set_budget(&mut self, budget: usize){
+        self.budget = budget.max(1);
+    }
+
+    /// Queues loading entities saved with `World::save_json` as a scene, the data is parsed right away
+    pub fn load_json(&mut self, registry: &TypeRegistry, scene: SceneId, data: &str) -> Result<(), SerializeError>{
+        self.queue(scene, World::load_json(registry, data)?);
+        Ok(())
+    }
+
+    /// Queues loading entities saved with `World::save_binary` as a scene, the data is parsed right away
+    pub fn load_binary(&mut self, registry: &TypeRegistry, scene: SceneId, data: &[u8]) -> Result<(), SerializeError>{
+        self.queue(scene, World::load_binary(registry, data)?);
+        Ok(())
+    }
+
+    fn queue(&mut self, scene: SceneId, staging: World){
+        let entities = if staging.contains::<EntityStorage>(){
+            staging.get::<EntityStorage>().live_entities()
+        }
+        else{
+            Vec::new()
+        };
+        self.loading.push_back(SceneLoad{
+            scene,
+            staging,
+            entities,
+            map: None,
+            next: 0,
+        });
+    }
+
+    /// Queues despawning every entity of a scene, cancelling the scene's loads that haven't finished
+    pub fn unload(&mut self, scene: SceneId){
+        self.unloading.push(scene);
+    }
+
+    pub fn is_loading(&self, scene: SceneId) -> bool{
+        self.loading.iter().any(|load| load.scene == scene)
+    }
+
+    pub fn events(&self) -> &[SceneEvent]{
+        &self.events
+    }
+
+    /// Takes the events since the last call
+    pub fn drain_events(&mut self) -> Vec<SceneEvent>{
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl World{
+    /// Despawns every entity of the scene with one pass over the scene storage and one over every other storage,
+    /// returns how many were despawned
+    pub fn unload_scene(&self, scene: SceneId) -> usize{
+        if !self.contains_comp::<SceneMember>(){
+            return 0;
+        }
+        let members: Vec<Entity> = {
+            let tags = self.get_comp::<SceneMember>();
+            self.get::<EntityStorage>().live_entities().into_iter()
+                .filter(|entity| tags.get(&entity.index()) == Some(&SceneMember(scene)))
+                .collect()
+        };
+        self.despawn_batch(&members)
+    }
+
+    /// Runs the queued unloads, then copies the next batch of entities of the scenes being loaded.
+    /// Uses the world's `SceneManager` and `TypeRegistry`, entity references are fixed up for the components
+    /// registered with `with_map_entities` and parent/child links are rebuilt once the whole scene is in.
+    pub fn update_scenes(&mut self){
+        let mut manager = match self.remove::<SceneManager>(){
+            Some(manager) => manager,
+            None => return,
+        };
+
+        for scene in std::mem::take(&mut manager.unloading){
+            manager.loading.retain(|load| load.scene != scene);
+            self.unload_scene(scene);
+            manager.events.push(SceneEvent::Unloaded(scene));
+        }
+
+        let mut budget = manager.budget;
+        while budget > 0{
+            let load = match manager.loading.front_mut(){
+                Some(load) => load,
+                None => break,
+            };
+
+            // Every entity is created up front so references into later batches can be mapped right away
+            if load.map.is_none(){
+                load.map = Some(self.allocate_scene(load));
+            }
+            let map = load.map.as_ref().unwrap();
+
+            let end = (load.next + budget).min(load.entities.len());
+            let batch = &load.entities[load.next..end];
+            load.staging.copy_components_to(batch, self, map);
+            self.tag_batch(load.scene, batch, map);
+            budget -= batch.len();
+            load.next = end;
+
+            if load.next == load.entities.len(){
+                let load = manager.loading.pop_front().unwrap();
+                let map = load.map.as_ref().unwrap();
+                load.staging.rebuild_hierarchy_in(&load.entities, self, map);
+                manager.events.push(SceneEvent::Loaded(load.scene));
+            }
+        }
+
+        self.insert(manager);
+    }
+
+    fn allocate_scene(&mut self, load: &SceneLoad) -> EntityMap{
+        load.staging.register_storages_in(self);
+        if !self.contains_comp::<SceneMember>(){
+            self.register_comp::<SceneMember>();
+        }
+
+        let mut map = EntityMap::new();
+        let mut entities = self.get_mut::<EntityStorage>();
+        let mut tags = self.get_comp_mut::<SceneMember>();
+        for entity in load.entities.iter(){
+            let new = *entities.create_entity();
+            tags.set(&new.index(), SceneMember(load.scene));
+            map.insert(*entity, new);
+        }
+        map
+    }
+
+    // Copied links still point at staging entities until the hierarchy is rebuilt, so they're dropped until then
+    fn tag_batch(&self, scene: SceneId, batch: &[Entity], map: &EntityMap){
+        let indices: Vec<usize> = batch.iter().map(|entity| map.map(entity).index()).collect();
+        {
+            let mut tags = self.get_comp_mut::<SceneMember>();
+            for index in indices.iter(){
+                tags.set(index, SceneMember(scene));
+            }
+        }
+        if self.has_hierarchy(){
+            let mut parents = self.get_comp_mut::<Parent>();
+            let mut children = self.get_comp_mut::<Children>();
+            for index in indices.iter(){
+                parents.delete(index);
+                children.delete(index);
+            }
+        }
+        if self.contains::<TypeRegistry>(){
+            for component in self.get::<TypeRegistry>().components(){
+                component.map_entities(self, map, &indices);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::entity_map::MapEntities;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    struct Follow(Entity);
+
+    impl MapEntities for Follow{
+        fn map_entities(&mut self, map: &EntityMap){
+            self.0.map_entities(map);
+        }
+    }
+
+    fn registry() -> TypeRegistry{
+        let mut registry = TypeRegistry::new();
+        registry.register_comp::<u32>("Id").with_serde();
+        registry.register_comp::<Follow>("Follow").with_serde().with_map_entities();
+        registry.register_comp::<Parent>("Parent").with_serde();
+        registry.register_comp::<Children>("Children").with_serde();
+        registry
+    }
+
+    // A chain where every entity follows the next and is the parent of the next
+    fn chunk(count: u32) -> String{
+        let mut world = World::new();
+        world.insert(EntityStorage::new());
+        registry().register_all(&mut world);
+        let entities: Vec<Entity> = (0..count).map(|_| *world.get_mut::<EntityStorage>().create_entity()).collect();
+        for (n, entity) in entities.iter().enumerate(){
+            world.get_comp_mut::<u32>().set(&entity.index(), n as u32);
+            if let Some(next) = entities.get(n + 1){
+                world.get_comp_mut::<Follow>().set(&entity.index(), Follow(*next));
+                world.set_parent(next, entity);
+            }
+        }
+        world.save_json(&registry()).unwrap()
+    }
+
+    fn world() -> World{
+        let mut world = World::new();
+        world.insert(EntityStorage::new());
+        world.insert(registry());
+        world.insert(SceneManager::new(2));
+        world.register_comp::<u32>();
+        // Something already in the world so the scene's entities get renumbered
+        let existing = *world.get_mut::<EntityStorage>().create_entity();
+        world.get_comp_mut::<u32>().set(&existing.index(), 100);
+        world
+    }
+
+    fn members(world: &World, scene: SceneId) -> Vec<Entity>{
+        let tags = world.get_comp::<SceneMember>();
+        world.get::<EntityStorage>().live_entities().into_iter()
+            .filter(|entity| tags.get(&entity.index()) == Some(&SceneMember(scene)))
+            .collect()
+    }
+
+    #[test]
+    fn load_over_frames(){
+        let mut world = world();
+        let data = chunk(5);
+        {
+            let registry = world.get::<TypeRegistry>();
+            world.get_mut::<SceneManager>().load_json(&registry, SceneId(1), &data).unwrap();
+        }
+
+        world.update_scenes();
+        world.update_scenes();
+        assert!(world.get_mut::<SceneManager>().drain_events().is_empty());
+        assert!(world.get::<SceneManager>().is_loading(SceneId(1)));
+        world.update_scenes();
+        assert_eq!(world.get_mut::<SceneManager>().drain_events(), vec![SceneEvent::Loaded(SceneId(1))]);
+
+        let entities = members(&world, SceneId(1));
+        assert_eq!(entities.len(), 5);
+        let ids = world.get_comp::<u32>();
+        let follows = world.get_comp::<Follow>();
+        for (n, entity) in entities.iter().enumerate(){
+            assert_eq!(*ids.get(&entity.index()).unwrap(), n as u32);
+            if let Some(next) = entities.get(n + 1){
+                assert!(follows.get(&entity.index()).unwrap().0 == *next);
+                assert!(world.children(entity) == vec![*next]);
+            }
+        }
+        assert_eq!(*ids.get(&0).unwrap(), 100);
+    }
+
+    #[test]
+    fn unload(){
+        let mut world = world();
+        let data = chunk(3);
+        {
+            let registry = world.get::<TypeRegistry>();
+            let mut manager = world.get_mut::<SceneManager>();
+            manager.set_budget(10);
+            manager.load_json(&registry, SceneId(1), &data).unwrap();
+            manager.load_json(&registry, SceneId(2), &data).unwrap();
+        }
+        world.update_scenes();
+        assert_eq!(world.get::<EntityStorage>().live_entities().len(), 7);
+
+        world.get_mut::<SceneManager>().unload(SceneId(1));
+        world.update_scenes();
+        assert!(members(&world, SceneId(1)).is_empty());
+        assert_eq!(members(&world, SceneId(2)).len(), 3);
+        assert_eq!(world.get::<EntityStorage>().live_entities().len(), 4);
+        assert_eq!(world.get::<SceneManager>().events().last(), Some(&SceneEvent::Unloaded(SceneId(1))));
+    }
+}
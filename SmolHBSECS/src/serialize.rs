@@ -0,0 +1,612 @@
+use crate::world::World;
+use crate::{Entity, EntityStorage};
+use crate::entity_map::EntityMap;
+use crate::registry::{TypeRegistry, ComponentBuilder, ResourceBuilder};
+use crate::component::{AnyStorage, VecStorage};
+use SmolCommon::WorldCommon;
+use SmolCommon::component::{Component, ComponentStorage};
+use SmolCommon::entity::EntityCommon;
+use SmolCommon::system::{SystemData, WriteComp};
+
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::any::Any;
+use std::fmt;
+
+type MergeJsonFn = fn(&World, Value, &HashMap<usize, usize>) -> Result<(), serde_json::Error>;
+type MergeBinaryFn = fn(&World, &[u8], &HashMap<usize, usize>) -> Result<(), bincode::Error>;
+type ResourceToJsonFn = fn(&World) -> Option<Result<Value, serde_json::Error>>;
+type ResourceToBinaryFn = fn(&World) -> Option<Result<Vec<u8>, bincode::Error>>;
+
+/// Type erased (de)serialization for one component storage
+pub(crate) struct ComponentSerde{
+    pub(crate) to_json: fn(&World) -> Result<Value, serde_json::Error>,
+    pub(crate) from_json: fn(&World, Value) -> Result<(), serde_json::Error>,
+    pub(crate) to_binary: fn(&World) -> Result<Vec<u8>, bincode::Error>,
+    pub(crate) from_binary: fn(&World, &[u8]) -> Result<(), bincode::Error>,
+    /// Like from_json but moves every entry to a new index, entries for unmapped indices are dropped
+    pub(crate) merge_json: MergeJsonFn,
+    pub(crate) merge_binary: MergeBinaryFn,
+    /// Saves only the entries at the given indices of a detached storage, in the same format as to_json
+    pub(crate) entries_to_json: fn(&dyn AnyStorage, &[usize]) -> Result<Value, serde_json::Error>,
+    /// Loads entries in the format of to_json into a new detached storage, leaving the world alone
+    pub(crate) entries_from_json: fn(Value) -> Result<Box<dyn AnyStorage>, serde_json::Error>,
+    /// Adds one component read from JSON to an entity
+    pub(crate) add_json: fn(&World, &Entity, Value) -> Result<(), serde_json::Error>,
+}
+
+/// Type erased (de)serialization for one resource, getters return None if the world doesn't have it
+pub(crate) struct ResourceSerde{
+    pub(crate) to_json: ResourceToJsonFn,
+    pub(crate) from_json: fn(&mut World, Value) -> Result<(), serde_json::Error>,
+    pub(crate) to_binary: ResourceToBinaryFn,
+    pub(crate) from_binary: fn(&mut World, &[u8]) -> Result<(), bincode::Error>,
+}
+
+#[derive(Debug)]
+pub enum SerializeError{
+    Json(serde_json::Error),
+    Binary(bincode::Error),
+    Ron(ron::error::SpannedError),
+    /// The data names a component that isn't in the registry
+    UnknownComponent(String),
+    /// The data names a resource that isn't in the registry
+    UnknownResource(String),
+    /// A prefab or a prefab it inherits from isn't loaded
+    UnknownPrefab(String),
+    /// A prefab ends up inheriting from itself
+    PrefabCycle(String),
+}
+
+impl fmt::Display for SerializeError{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        match self{
+            SerializeError::Json(e) => write!(f, "json error: {}", e),
+            SerializeError::Binary(e) => write!(f, "binary error: {}", e),
+            SerializeError::Ron(e) => write!(f, "ron error: {}", e),
+            SerializeError::UnknownComponent(name) => write!(f, "unknown component {}", name),
+            SerializeError::UnknownResource(name) => write!(f, "unknown resource {}", name),
+            SerializeError::UnknownPrefab(name) => write!(f, "unknown prefab {}", name),
+            SerializeError::PrefabCycle(name) => write!(f, "prefab {} inherits from itself", name),
+        }
+    }
+}
+
+impl std::error::Error for SerializeError{}
+
+impl From<serde_json::Error> for SerializeError{
+    fn from(e: serde_json::Error) -> Self{
+        SerializeError::Json(e)
+    }
+}
+
+impl From<bincode::Error> for SerializeError{
+    fn from(e: bincode::Error) -> Self{
+        SerializeError::Binary(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for SerializeError{
+    fn from(e: ron::error::SpannedError) -> Self{
+        SerializeError::Ron(e)
+    }
+}
+
+// Storages are saved as (entity index, component) pairs for every valid entry
+fn entries<T: 'static + Component>(world: &World) -> Vec<(usize, T)>{
+    world.get_comp::<T>().iter()
+        .enumerate()
+        .filter_map(|(index, (valid, comp))| if valid { comp.map(|c| (index, *c)) } else { None })
+        .collect()
+}
+
+fn set_entries<T: 'static + Component>(world: &World, entries: Vec<(usize, T)>){
+    let mut storage = world.get_comp_mut::<T>();
+    for (index, comp) in entries{
+        storage.set(&index, comp);
+    }
+}
+
+fn set_entries_mapped<T: 'static + Component>(world: &World, entries: Vec<(usize, T)>, indices: &HashMap<usize, usize>){
+    let mut storage = world.get_comp_mut::<T>();
+    for (index, comp) in entries{
+        if let Some(new_index) = indices.get(&index){
+            storage.set(new_index, comp);
+        }
+    }
+}
+
+fn storage_to_json<T: 'static + Component + Serialize>(world: &World) -> Result<Value, serde_json::Error>{
+    serde_json::to_value(entries::<T>(world))
+}
+
+fn storage_from_json<T: 'static + Component + DeserializeOwned>(world: &World, value: Value) -> Result<(), serde_json::Error>{
+    set_entries(world, serde_json::from_value::<Vec<(usize, T)>>(value)?);
+    Ok(())
+}
+
+fn storage_to_binary<T: 'static + Component + Serialize>(world: &World) -> Result<Vec<u8>, bincode::Error>{
+    bincode::serialize(&entries::<T>(world))
+}
+
+fn storage_from_binary<T: 'static + Component + DeserializeOwned>(world: &World, bytes: &[u8]) -> Result<(), bincode::Error>{
+    set_entries(world, bincode::deserialize::<Vec<(usize, T)>>(bytes)?);
+    Ok(())
+}
+
+fn storage_merge_json<T: 'static + Component + DeserializeOwned>(world: &World, value: Value, indices: &HashMap<usize, usize>) -> Result<(), serde_json::Error>{
+    set_entries_mapped(world, serde_json::from_value::<Vec<(usize, T)>>(value)?, indices);
+    Ok(())
+}
+
+fn storage_merge_binary<T: 'static + Component + DeserializeOwned>(world: &World, bytes: &[u8], indices: &HashMap<usize, usize>) -> Result<(), bincode::Error>{
+    set_entries_mapped(world, bincode::deserialize::<Vec<(usize, T)>>(bytes)?, indices);
+    Ok(())
+}
+
+fn storage_entries_to_json<T: 'static + Component + Serialize>(storage: &dyn AnyStorage, indices: &[usize]) -> Result<Value, serde_json::Error>{
+    let storage = storage.as_any().downcast_ref::<VecStorage<T>>().unwrap();
+    let entries: Vec<(usize, T)> = indices.iter()
+        .filter_map(|index| storage.get(index).map(|comp| (*index, *comp)))
+        .collect();
+    serde_json::to_value(entries)
+}
+
+fn storage_entries_from_json<T: 'static + Component + DeserializeOwned>(value: Value) -> Result<Box<dyn AnyStorage>, serde_json::Error>{
+    let mut storage = VecStorage::<T>::new();
+    for (index, comp) in serde_json::from_value::<Vec<(usize, T)>>(value)?{
+        storage.set(&index, comp);
+    }
+    Ok(Box::new(storage))
+}
+
+fn storage_add_json<T: 'static + Component + DeserializeOwned>(world: &World, entity: &Entity, value: Value) -> Result<(), serde_json::Error>{
+    let comp = serde_json::from_value::<T>(value)?;
+    entity.add(&mut WriteComp::<T>::get_data(world), comp);
+    Ok(())
+}
+
+fn resource_to_json<T: Any + Serialize>(world: &World) -> Option<Result<Value, serde_json::Error>>{
+    if !world.contains::<T>(){
+        return None;
+    }
+    Some(serde_json::to_value(&*world.get::<T>()))
+}
+
+fn resource_from_json<T: Any + DeserializeOwned>(world: &mut World, value: Value) -> Result<(), serde_json::Error>{
+    world.insert(serde_json::from_value::<T>(value)?);
+    Ok(())
+}
+
+fn resource_to_binary<T: Any + Serialize>(world: &World) -> Option<Result<Vec<u8>, bincode::Error>>{
+    if !world.contains::<T>(){
+        return None;
+    }
+    Some(bincode::serialize(&*world.get::<T>()))
+}
+
+fn resource_from_binary<T: Any + DeserializeOwned>(world: &mut World, bytes: &[u8]) -> Result<(), bincode::Error>{
+    world.insert(bincode::deserialize::<T>(bytes)?);
+    Ok(())
+}
+
+impl<'r, T> ComponentBuilder<'r, T>
+    where T: 'static + Component + Serialize + DeserializeOwned{
+    /// Includes this component's storage when saving and loading worlds
+    pub fn with_serde(self) -> Self{
+        self.registration.serde = Some(ComponentSerde{
+            to_json: storage_to_json::<T>,
+            from_json: storage_from_json::<T>,
+            to_binary: storage_to_binary::<T>,
+            from_binary: storage_from_binary::<T>,
+            merge_json: storage_merge_json::<T>,
+            merge_binary: storage_merge_binary::<T>,
+            entries_to_json: storage_entries_to_json::<T>,
+            entries_from_json: storage_entries_from_json::<T>,
+            add_json: storage_add_json::<T>,
+        });
+        self
+    }
+}
+
+impl<'r, T> ResourceBuilder<'r, T>
+    where T: Any + Serialize + DeserializeOwned{
+    /// Includes this resource when saving and loading worlds
+    pub fn with_serde(self) -> Self{
+        self.registration.serde = Some(ResourceSerde{
+            to_json: resource_to_json::<T>,
+            from_json: resource_from_json::<T>,
+            to_binary: resource_to_binary::<T>,
+            from_binary: resource_from_binary::<T>,
+        });
+        self
+    }
+}
+
+/// Saved form of a world, components and resources are keyed by their registered names
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedWorld<D>{
+    pub(crate) entities: Option<EntityStorage>,
+    pub(crate) components: BTreeMap<String, D>,
+    pub(crate) resources: BTreeMap<String, D>,
+}
+
+impl World{
+    /// Saves the entities, every registered storage, and the registered resources as JSON
+    pub fn save_json(&self, registry: &TypeRegistry) -> Result<String, SerializeError>{
+        let mut saved = SavedWorld{
+            entities: self.saved_entities(),
+            components: BTreeMap::new(),
+            resources: BTreeMap::new(),
+        };
+
+        for component in registry.components(){
+            if let Some(serde) = &component.serde{
+                if self.contains_comp_id(component.type_id()){
+                    saved.components.insert(component.name().to_string(), (serde.to_json)(self)?);
+                }
+            }
+        }
+
+        for resource in registry.resources(){
+            if let Some(value) = resource.serde.as_ref().and_then(|serde| (serde.to_json)(self)){
+                saved.resources.insert(resource.name().to_string(), value?);
+            }
+        }
+
+        Ok(serde_json::to_string_pretty(&saved)?)
+    }
+
+    /// Builds a new world from JSON written by `save_json`
+    pub fn load_json(registry: &TypeRegistry, data: &str) -> Result<World, SerializeError>{
+        let saved: SavedWorld<Value> = serde_json::from_str(data)?;
+        let mut world = World::new_for_load(registry, saved.entities);
+
+        for (name, value) in saved.components{
+            let serde = component_serde(registry, &name)?;
+            (serde.from_json)(&world, value)?;
+        }
+
+        for (name, value) in saved.resources{
+            let serde = resource_serde(registry, &name)?;
+            (serde.from_json)(&mut world, value)?;
+        }
+
+        Ok(world)
+    }
+
+    /// Saves the entities, every registered storage, and the registered resources in a compact binary format
+    pub fn save_binary(&self, registry: &TypeRegistry) -> Result<Vec<u8>, SerializeError>{
+        let mut saved = SavedWorld{
+            entities: self.saved_entities(),
+            components: BTreeMap::new(),
+            resources: BTreeMap::new(),
+        };
+
+        for component in registry.components(){
+            if let Some(serde) = &component.serde{
+                if self.contains_comp_id(component.type_id()){
+                    saved.components.insert(component.name().to_string(), (serde.to_binary)(self)?);
+                }
+            }
+        }
+
+        for resource in registry.resources(){
+            if let Some(bytes) = resource.serde.as_ref().and_then(|serde| (serde.to_binary)(self)){
+                saved.resources.insert(resource.name().to_string(), bytes?);
+            }
+        }
+
+        Ok(bincode::serialize(&saved)?)
+    }
+
+    /// Builds a new world from data written by `save_binary`
+    pub fn load_binary(registry: &TypeRegistry, data: &[u8]) -> Result<World, SerializeError>{
+        let saved: SavedWorld<Vec<u8>> = bincode::deserialize(data)?;
+        let mut world = World::new_for_load(registry, saved.entities);
+
+        for (name, bytes) in saved.components{
+            let serde = component_serde(registry, &name)?;
+            (serde.from_binary)(&world, &bytes)?;
+        }
+
+        for (name, bytes) in saved.resources{
+            let serde = resource_serde(registry, &name)?;
+            (serde.from_binary)(&mut world, &bytes)?;
+        }
+
+        Ok(world)
+    }
+
+    /// Adds the entities in JSON written by `save_json` to this world as new entities.
+    /// Returns which new entity every saved entity became, entity references inside components
+    /// that opted into `with_map_entities` are rewritten with it. Saved resources are ignored.
+    /// On an error the new entities are deleted again, the world keeps the storages the registry registered.
+    pub fn merge_json(&mut self, registry: &TypeRegistry, data: &str) -> Result<EntityMap, SerializeError>{
+        let saved: SavedWorld<Value> = serde_json::from_str(data)?;
+        let components = resolve_components(registry, saved.components)?;
+        let (map, indices) = self.allocate_merged(registry, saved.entities.as_ref());
+
+        let merged = components.into_iter()
+            .try_for_each(|(serde, value)| (serde.merge_json)(self, value, &indices).map_err(SerializeError::from));
+        self.finish_merge(registry, map, merged)
+    }
+
+    /// Adds the entities in data written by `save_binary` to this world as new entities, see `merge_json`
+    pub fn merge_binary(&mut self, registry: &TypeRegistry, data: &[u8]) -> Result<EntityMap, SerializeError>{
+        let saved: SavedWorld<Vec<u8>> = bincode::deserialize(data)?;
+        let components = resolve_components(registry, saved.components)?;
+        let (map, indices) = self.allocate_merged(registry, saved.entities.as_ref());
+
+        let merged = components.into_iter()
+            .try_for_each(|(serde, bytes)| (serde.merge_binary)(self, &bytes, &indices).map_err(SerializeError::from));
+        self.finish_merge(registry, map, merged)
+    }
+
+    fn finish_merge(&self, registry: &TypeRegistry, map: EntityMap, merged: Result<(), SerializeError>) -> Result<EntityMap, SerializeError>{
+        match merged{
+            Ok(()) => {
+                registry.map_entities(self, &map);
+                Ok(map)
+            },
+            Err(err) => {
+                // Hierarchy links in the merged data weren't mapped yet, so they're deleted without following them
+                let new: Vec<Entity> = map.iter().map(|(_, new)| *new).collect();
+                self.delete_entities(&new);
+                Err(err)
+            },
+        }
+    }
+
+    // Creates a fresh entity for every live saved entity
+    fn allocate_merged(&mut self, registry: &TypeRegistry, saved: Option<&EntityStorage>) -> (EntityMap, HashMap<usize, usize>){
+        if !self.contains::<EntityStorage>(){
+            self.insert(EntityStorage::new());
+        }
+        registry.register_all(self);
+
+        let mut map = EntityMap::new();
+        let mut indices = HashMap::new();
+        let mut entities = self.get_mut::<EntityStorage>();
+        for old in saved.map(|saved| saved.live_entities()).unwrap_or_default(){
+            let new = *entities.create_entity();
+            map.insert(old, new);
+            indices.insert(old.index(), new.index());
+        }
+        (map, indices)
+    }
+
+    fn saved_entities(&self) -> Option<EntityStorage>{
+        if self.contains::<EntityStorage>(){
+            Some(self.get::<EntityStorage>().clone())
+        }
+        else{
+            None
+        }
+    }
+
+    fn new_for_load(registry: &TypeRegistry, entities: Option<EntityStorage>) -> World{
+        let mut world = World::new();
+        if let Some(entities) = entities{
+            world.insert(entities);
+        }
+        registry.register_all(&mut world);
+        world
+    }
+}
+
+// Looks up every saved component before anything is changed, so an unknown name leaves the world alone
+fn resolve_components<D>(registry: &TypeRegistry, components: BTreeMap<String, D>) -> Result<Vec<(&ComponentSerde, D)>, SerializeError>{
+    components.into_iter()
+        .map(|(name, data)| Ok((component_serde(registry, &name)?, data)))
+        .collect()
+}
+
+pub(crate) fn component_serde<'r>(registry: &'r TypeRegistry, name: &str) -> Result<&'r ComponentSerde, SerializeError>{
+    registry.component(name)
+        .and_then(|component| component.serde.as_ref())
+        .ok_or_else(|| SerializeError::UnknownComponent(name.to_string()))
+}
+
+pub(crate) fn resource_serde<'r>(registry: &'r TypeRegistry, name: &str) -> Result<&'r ResourceSerde, SerializeError>{
+    registry.resource(name)
+        .and_then(|resource| resource.serde.as_ref())
+        .ok_or_else(|| SerializeError::UnknownResource(name.to_string()))
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use SmolCommon::system::ReadComp;
+    use SmolCommon::join::Joinable;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    struct Position{
+        x: f32,
+        y: f32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Gravity(f32);
+
+    fn registry() -> TypeRegistry{
+        let mut registry = TypeRegistry::new();
+        registry.register_comp::<Position>("Position").with_serde();
+        registry.register_comp::<u32>("Health").with_serde();
+        registry.register_res::<Gravity>("Gravity").with_serde();
+        registry
+    }
+
+    fn world() -> World{
+        let mut world = World::new();
+        world.register_comp::<Position>();
+        world.register_comp::<u32>();
+        world.insert(Gravity(-9.8));
+        world.insert(EntityStorage::new());
+
+        let mut entities: Vec<Entity> = (0..4).map(|_| *world.get_mut::<EntityStorage>().create_entity()).collect();
+        world.get_mut::<EntityStorage>().delete_entity(&entities[1]);
+        entities[1] = *world.get_mut::<EntityStorage>().create_entity();
+
+        {
+            let mut positions = WriteComp::<Position>::get_data(&world);
+            let mut health = WriteComp::<u32>::get_data(&world);
+            for (n, entity) in entities.iter().enumerate(){
+                entity.add(&mut positions, Position{x: n as f32, y: 2.0 * n as f32});
+                if n % 2 == 0{
+                    entity.add(&mut health, 100 + n as u32);
+                }
+            }
+        }
+        world
+    }
+
+    fn check(original: &World, loaded: &World){
+        let positions: Vec<Position> = (&ReadComp::<Position>::get_data(original)).join().copied().collect();
+        let loaded_positions: Vec<Position> = (&ReadComp::<Position>::get_data(loaded)).join().copied().collect();
+        assert_eq!(positions, loaded_positions);
+
+        let health: Vec<u32> = (&ReadComp::<u32>::get_data(original)).join().copied().collect();
+        let loaded_health: Vec<u32> = (&ReadComp::<u32>::get_data(loaded)).join().copied().collect();
+        assert_eq!(health, loaded_health);
+
+        let entities: Vec<Entity> = (&*original.get::<EntityStorage>()).join().cloned().collect();
+        let loaded_entities: Vec<Entity> = (&*loaded.get::<EntityStorage>()).join().cloned().collect();
+        assert!(entities == loaded_entities);
+        assert_eq!(loaded_entities[1].generation, 1);
+
+        assert_eq!(*loaded.get::<Gravity>(), Gravity(-9.8));
+    }
+
+    #[test]
+    fn json_round_trip(){
+        let registry = registry();
+        let world = world();
+
+        let json = world.save_json(&registry).unwrap();
+        let loaded = World::load_json(&registry, &json).unwrap();
+        check(&world, &loaded);
+    }
+
+    #[test]
+    fn binary_round_trip(){
+        let registry = registry();
+        let world = world();
+
+        let bytes = world.save_binary(&registry).unwrap();
+        let loaded = World::load_binary(&registry, &bytes).unwrap();
+        check(&world, &loaded);
+    }
+
+    #[test]
+    fn unknown_component(){
+        let world = world();
+        let json = world.save_json(&registry()).unwrap();
+
+        let mut missing = TypeRegistry::new();
+        missing.register_comp::<Position>("Position").with_serde();
+        match World::load_json(&missing, &json){
+            Err(SerializeError::UnknownComponent(name)) => assert_eq!(name, "Health"),
+            _ => panic!("Loading should fail on the unregistered component"),
+        }
+    }
+
+    #[derive(Clone, Copy, Serialize, Deserialize)]
+    struct Follow{
+        target: Entity,
+    }
+
+    impl crate::entity_map::MapEntities for Follow{
+        fn map_entities(&mut self, map: &EntityMap){
+            self.target = map.map(&self.target);
+        }
+    }
+
+    #[test]
+    fn merge_remaps_entities(){
+        let mut registry = registry();
+        registry.register_comp::<Follow>("Follow").with_serde().with_map_entities();
+
+        let mut saved = world();
+        saved.register_comp::<Follow>();
+        let entities = saved.get::<EntityStorage>().live_entities();
+        for (n, entity) in entities.iter().enumerate(){
+            let target = entities[(n + 1) % entities.len()];
+            saved.get_comp_mut::<Follow>().set(&entity.index, Follow{target});
+        }
+        let json = saved.save_json(&registry).unwrap();
+
+        let mut live = World::new();
+        live.insert(EntityStorage::new());
+        live.register_comp::<Position>();
+        for _ in 0..3{
+            let entity = *live.get_mut::<EntityStorage>().create_entity();
+            live.get_comp_mut::<Position>().set(&entity.index, Position{x: -1.0, y: -1.0});
+        }
+
+        let map = live.merge_json(&registry, &json).unwrap();
+        assert_eq!(map.len(), 4);
+        assert_eq!(live.get::<EntityStorage>().live_entities().len(), 7);
+
+        for (n, old) in entities.iter().enumerate(){
+            let new = map.get(old).unwrap();
+            assert!(new.index >= 3);
+            assert_eq!(*live.get_comp::<Position>().get(&new.index).unwrap(), Position{x: n as f32, y: 2.0 * n as f32});
+
+            let follow = *live.get_comp::<Follow>().get(&new.index).unwrap();
+            assert!(follow.target == map.get(&entities[(n + 1) % entities.len()]).unwrap());
+        }
+
+        // Entities that were already there are untouched
+        assert_eq!(*live.get_comp::<Position>().get(&0).unwrap(), Position{x: -1.0, y: -1.0});
+    }
+
+    fn live_world() -> World{
+        let mut live = World::new();
+        live.insert(EntityStorage::new());
+        live.register_comp::<u32>();
+        let entity = *live.get_mut::<EntityStorage>().create_entity();
+        live.get_comp_mut::<u32>().set(&entity.index, 7);
+        live
+    }
+
+    #[test]
+    fn merge_unknown_component_changes_nothing(){
+        let mut registry = registry();
+        registry.register_comp::<Follow>("Follow").with_serde();
+        let mut saved = world();
+        saved.register_comp::<Follow>();
+        let json = saved.save_json(&registry).unwrap();
+        let bytes = saved.save_binary(&registry).unwrap();
+
+        let mut live = live_world();
+        // Registered but not serializable, the name can't be resolved for loading
+        let mut without_serde = self::registry();
+        without_serde.register_comp::<Follow>("Follow");
+        assert!(matches!(live.merge_json(&without_serde, &json), Err(SerializeError::UnknownComponent(name)) if name == "Follow"));
+        assert!(matches!(live.merge_binary(&self::registry(), &bytes), Err(SerializeError::UnknownComponent(_))));
+        assert_eq!(live.get::<EntityStorage>().live_entities().len(), 1);
+    }
+
+    #[test]
+    fn merge_bad_value_rolls_back(){
+        let registry = registry();
+        let mut json: Value = serde_json::from_str(&world().save_json(&registry).unwrap()).unwrap();
+        // Health comes before Position, so its components are already set when Position fails
+        json["components"]["Position"][2][1]["x"] = Value::from("far");
+
+        let mut live = live_world();
+        assert!(matches!(live.merge_json(&registry, &json.to_string()), Err(SerializeError::Json(_))));
+        assert_eq!(live.get::<EntityStorage>().live_entities().len(), 1);
+        assert_eq!(live.get_comp::<u32>().iter().filter(|(valid, _)| *valid).count(), 1);
+        assert_eq!(live.get_comp::<Position>().iter().filter(|(valid, _)| *valid).count(), 0);
+        assert_eq!(*live.get_comp::<u32>().get(&0).unwrap(), 7);
+
+        // The slots freed by the rollback get reused
+        let map = live.merge_json(&registry, &world().save_json(&registry).unwrap()).unwrap();
+        assert_eq!(live.get::<EntityStorage>().live_entities().len(), 5);
+        assert!(map.iter().all(|(_, new)| new.index < 5));
+    }
+}
@@ -0,0 +1,228 @@
+use crate::world::World;
+use crate::EntityStorage;
+use crate::component::AnyStorage;
+use SmolCommon::WorldCommon;
+use std::any::TypeId;
+use std::collections::{HashMap, VecDeque};
+
+/// A copy of every component storage and the entity storage of a world.
+/// Resources other than the EntityStorage aren't included.
+pub struct Snapshot{
+    components: HashMap<TypeId, Box<dyn AnyStorage>>,
+    entities: Option<EntityStorage>,
+}
+
+impl Snapshot{
+    pub fn storage(&self, id: &TypeId) -> Option<&dyn AnyStorage>{
+        self.components.get(id).map(|storage| storage.as_ref())
+    }
+
+    pub fn storage_ids(&self) -> impl Iterator<Item = &TypeId>{
+        self.components.keys()
+    }
+
+    pub fn entities(&self) -> Option<&EntityStorage>{
+        self.entities.as_ref()
+    }
+}
+
+impl World{
+    /// Copies the state of every storage and the entities, components are Copy so this is a straight memory copy
+    pub fn snapshot(&self) -> Snapshot{
+        let components = self.storages()
+            .map(|(id, storage)| (*id, storage.read().clone_storage()))
+            .collect();
+
+        let entities = if self.contains::<EntityStorage>(){
+            Some(self.get::<EntityStorage>().clone())
+        }
+        else{
+            None
+        };
+
+        Snapshot{
+            components,
+            entities,
+        }
+    }
+
+    /// Puts every storage and the entities back the way they were when the snapshot was taken.
+    /// Storages registered after the snapshot was taken are emptied, the EntityStorage is inserted if it's gone.
+    pub fn restore(&mut self, snapshot: &Snapshot){
+        for (id, storage) in self.storages(){
+            match snapshot.components.get(id){
+                Some(saved) => storage.write().copy_from(saved.as_ref()),
+                None => storage.write().clear(),
+            }
+        }
+
+        if let Some(entities) = &snapshot.entities{
+            if self.contains::<EntityStorage>(){
+                self.get_mut::<EntityStorage>().clone_from(entities);
+            }
+            else{
+                self.insert(entities.clone());
+            }
+        }
+    }
+}
+
+/// Keeps snapshots of the last few frames for rollback.
+/// The snapshot for a frame is the state at the start of that frame, before it was simulated.
+pub struct SnapshotRing{
+    capacity: usize,
+    frames: VecDeque<(u64, Snapshot)>,
+}
+
+impl SnapshotRing{
+    pub fn new(capacity: usize) -> Self{
+        SnapshotRing{
+            capacity: capacity.max(1),
+            frames: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Snapshots the world as the start of `frame`, replacing any snapshots from that frame on
+    pub fn record(&mut self, world: &World, frame: u64){
+        while self.frames.back().is_some_and(|(f, _)| *f >= frame){
+            self.frames.pop_back();
+        }
+        if self.frames.len() == self.capacity{
+            self.frames.pop_front();
+        }
+        self.frames.push_back((frame, world.snapshot()));
+    }
+
+    pub fn get(&self, frame: u64) -> Option<&Snapshot>{
+        self.frames.iter().find(|(f, _)| *f == frame).map(|(_, snapshot)| snapshot)
+    }
+
+    /// Earliest frame that can still be rolled back to
+    pub fn oldest_frame(&self) -> Option<u64>{
+        self.frames.front().map(|(f, _)| *f)
+    }
+
+    pub fn newest_frame(&self) -> Option<u64>{
+        self.frames.back().map(|(f, _)| *f)
+    }
+
+    pub fn len(&self) -> usize{
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.frames.is_empty()
+    }
+
+    /// Restores the world to the start of `frame` and drops every later snapshot, false if the frame isn't kept anymore
+    pub fn restore(&mut self, world: &mut World, frame: u64) -> bool{
+        match self.get(frame){
+            Some(snapshot) => world.restore(snapshot),
+            None => return false,
+        }
+        while self.frames.back().is_some_and(|(f, _)| *f > frame){
+            self.frames.pop_back();
+        }
+        true
+    }
+
+    /// Restores the world to the start of `frame` then simulates forward to the start of `current`,
+    /// calling `step` once per frame and recording new snapshots along the way
+    pub fn rollback<F: FnMut(&World, u64)>(&mut self, world: &mut World, frame: u64, current: u64, mut step: F) -> bool{
+        if !self.restore(world, frame){
+            return false;
+        }
+        for f in frame..current{
+            step(world, f);
+            self.record(world, f + 1);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::Entity;
+    use SmolCommon::system::{SystemData, ReadComp, WriteComp};
+    use SmolCommon::join::Joinable;
+
+    fn world() -> World{
+        let mut world = World::new();
+        world.register_comp::<u64>();
+        world.register_comp::<i8>();
+        world.insert(EntityStorage::new());
+
+        for i in 0..8{
+            let entity = *world.get_mut::<EntityStorage>().create_entity();
+            world.get_comp_mut::<u64>().set(&entity.index, i);
+            if i % 2 == 0{
+                world.get_comp_mut::<i8>().set(&entity.index, -1);
+            }
+        }
+        world
+    }
+
+    fn values(world: &World) -> Vec<u64>{
+        (&ReadComp::<u64>::get_data(world)).join().copied().collect()
+    }
+
+    #[test]
+    fn snapshot_restore(){
+        let mut world = world();
+        let snapshot = world.snapshot();
+        let before = values(&world);
+
+        for value in (&mut WriteComp::<u64>::get_data(&world)).join(){
+            *value *= 10;
+        }
+        world.get_comp_mut::<u64>().delete(&3);
+        world.get_comp_mut::<i8>().set(&1, 5);
+        let entity = *world.get_mut::<EntityStorage>().create_entity();
+        world.get_comp_mut::<u64>().set(&entity.index, 99);
+
+        world.restore(&snapshot);
+
+        assert_eq!(values(&world), before);
+        assert!(world.get_comp::<i8>().get(&1).is_none());
+        assert_eq!(world.get::<EntityStorage>().live_entities().len(), 8);
+        assert!(!world.get::<EntityStorage>().is_alive(&entity));
+
+        // The entities come back even if the storage was taken out
+        world.remove::<EntityStorage>();
+        world.restore(&snapshot);
+        assert_eq!(world.get::<EntityStorage>().live_entities().len(), 8);
+    }
+
+    fn step(world: &World, frame: u64){
+        for value in (&mut WriteComp::<u64>::get_data(world)).join(){
+            *value += frame;
+        }
+    }
+
+    #[test]
+    fn rollback_resimulates(){
+        let mut world = world();
+        let mut ring = SnapshotRing::new(4);
+
+        for frame in 0..10{
+            ring.record(&world, frame);
+            step(&world, frame);
+        }
+        let expected = values(&world);
+        assert_eq!(ring.oldest_frame(), Some(6));
+        assert_eq!(ring.len(), 4);
+
+        // Too old to roll back to
+        assert!(!ring.rollback(&mut world, 2, 10, step));
+
+        world.get_comp_mut::<u64>().delete(&0);
+        let despawned: Entity = world.get::<EntityStorage>().live_entities()[0];
+        world.get_mut::<EntityStorage>().delete_entity(&despawned);
+
+        assert!(ring.rollback(&mut world, 7, 10, step));
+        assert_eq!(values(&world), expected);
+        assert!(world.get::<EntityStorage>().is_alive(&despawned));
+        assert_eq!(ring.newest_frame(), Some(10));
+    }
+}
@@ -0,0 +1,167 @@
+use crate::world::World;
+use crate::system::SystemScheduler;
+use crate::command::Commands;
+use SmolCommon::WorldCommon;
+use SmolCommon::system::{Scheduler, IntoSystem};
+use std::sync::Arc;
+use rayon;
+
+pub const PRE_UPDATE: &str = "PreUpdate";
+pub const UPDATE: &str = "Update";
+pub const POST_UPDATE: &str = "PostUpdate";
+
+/// Runs labeled schedulers one after another, applying queued `Commands` after each one.
+/// Every stage finishes before the next starts, so systems in a later stage see
+/// all the changes made and queued by earlier ones.
+pub struct Stages<'d, 'w: 'd>{
+    stages: Vec<(String, SystemScheduler<'d, 'w>)>,
+    pool: Arc<rayon::ThreadPool>,
+}
+
+impl<'d, 'w: 'd> Stages<'d, 'w>{
+    /// Creates an empty set of stages, the world needs a `Commands` resource to run them
+    pub fn new(pool: Arc<rayon::ThreadPool>) -> Self{
+        Stages{
+            stages: Vec::new(),
+            pool,
+        }
+    }
+
+    /// Creates the PreUpdate, Update, and PostUpdate stages
+    pub fn with_defaults(pool: Arc<rayon::ThreadPool>) -> Self{
+        let mut stages = Stages::new(pool);
+        stages.add_stage(PRE_UPDATE);
+        stages.add_stage(UPDATE);
+        stages.add_stage(POST_UPDATE);
+        stages
+    }
+
+    /// Adds a stage that runs after all current stages
+    pub fn add_stage(&mut self, label: &str){
+        let index = self.stages.len();
+        self.insert_stage(index, label);
+    }
+
+    pub fn add_stage_before(&mut self, label: &str, before: &str){
+        let index = self.position(before);
+        self.insert_stage(index, label);
+    }
+
+    pub fn add_stage_after(&mut self, label: &str, after: &str){
+        let index = self.position(after) + 1;
+        self.insert_stage(index, label);
+    }
+
+    /// Gets a stage's scheduler, to add systems or configure sets in it
+    pub fn stage(&mut self, label: &str) -> &mut SystemScheduler<'d, 'w>{
+        let index = self.position(label);
+        &mut self.stages[index].1
+    }
+
+    pub fn add_to_stage<M, S: IntoSystem<'d, 'w, World, M>>(&mut self, label: &str, system: S, name: &str, dep: Vec<&str>)
+        where S::System: 'w{
+        self.stage(label).add(system, name, dep);
+    }
+
+    pub fn labels(&self) -> Vec<&str>{
+        self.stages.iter().map(|(label, _)| label.as_str()).collect()
+    }
+
+    fn insert_stage(&mut self, index: usize, label: &str){
+        assert!(self.stages.iter().all(|(l, _)| l != label), "Stage {} already exists", label);
+        self.stages.insert(index, (label.to_string(), SystemScheduler::new(self.pool.clone())));
+    }
+
+    fn position(&self, label: &str) -> usize{
+        match self.stages.iter().position(|(l, _)| l == label){
+            Some(index) => index,
+            None => panic!("No stage labeled {}", label),
+        }
+    }
+}
+
+impl<'d, 'w: 'd> Scheduler<'d, 'w, World> for Stages<'d, 'w>{
+
+    /// Adds the system to the Update stage
+    fn add<M, S: IntoSystem<'d, 'w, World, M>>(&mut self, system: S, name: &str, dep: Vec<&str>)
+        where S::System: 'w{
+        self.add_to_stage(UPDATE, system, name, dep);
+    }
+
+    fn run(&self, world: &'w World){
+        for (_, stage) in self.stages.iter(){
+            stage.run(world);
+            Commands::apply(world);
+        }
+    }
+
+    /// Sets up every stage and inserts the `Commands` resource stages flush
+    fn setup(&self, world: &mut World){
+        if !world.contains::<Commands>(){
+            world.insert(Commands::new());
+        }
+        for (_, stage) in self.stages.iter(){
+            stage.setup(world);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::{Entity, EntityStorage};
+    use SmolCommon::system::*;
+    use SmolCommon::join::Joinable;
+
+    struct SpawnOne;
+
+    impl<'d, 'w: 'd> System<'d, 'w, World> for SpawnOne{
+        type SystemData = Write<'d, Commands>;
+
+        fn run(&self, mut commands: Self::SystemData){
+            commands.spawn(|world, entity: &Entity|{
+                world.get_comp_mut::<u32>().set(&entity.index, 7);
+            });
+        }
+    }
+
+    struct CountSpawned;
+
+    impl<'d, 'w: 'd> System<'d, 'w, World> for CountSpawned{
+        type SystemData = (ReadComp<'d, u32>, Write<'d, usize>);
+
+        fn run(&self, (nums, mut count): Self::SystemData){
+            *count = (&nums).join().count();
+        }
+    }
+
+    #[test]
+    fn commands_flush_between_stages(){
+        let mut world = World::new();
+        world.register_comp::<u32>();
+        world.insert(EntityStorage::new());
+        world.insert(Commands::new());
+        world.insert(0_usize);
+
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+        let mut stages = Stages::with_defaults(pool);
+        stages.add_to_stage(PRE_UPDATE, SpawnOne{}, "SpawnOne", vec![]);
+        stages.add(CountSpawned{}, "CountSpawned", vec![]);
+
+        stages.run(&world);
+        assert_eq!(*world.get::<usize>(), 1);
+        stages.run(&world);
+        assert_eq!(*world.get::<usize>(), 2);
+        assert!(world.get::<Commands>().is_empty());
+    }
+
+    #[test]
+    fn stage_order(){
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+        let mut stages = Stages::with_defaults(pool);
+        stages.add_stage_before("First", PRE_UPDATE);
+        stages.add_stage_after("Render", UPDATE);
+
+        assert_eq!(stages.labels(), vec!["First", PRE_UPDATE, UPDATE, "Render", POST_UPDATE]);
+    }
+}
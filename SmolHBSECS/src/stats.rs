@@ -0,0 +1,288 @@
+use crate::world::World;
+use crate::{Entity, EntityStorage};
+use SmolCommon::WorldCommon;
+use bit_vec::BitVec;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// A combination of components some entities have, what an archetype ECS would store them as
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Archetype{
+    /// Type names, in storage order
+    pub components: Vec<&'static str>,
+    pub count: usize,
+}
+
+/// How full a storage is. Storages grow up to the highest index that was ever set, every unset index below is a hole.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct StorageStats{
+    pub component: &'static str,
+    pub occupied: usize,
+    /// Slots the storage has
+    pub len: usize,
+}
+
+impl StorageStats{
+    pub fn holes(&self) -> usize{
+        self.len - self.occupied
+    }
+
+    /// Fraction of the storage's slots that are empty, 0 for an empty storage
+    pub fn hole_ratio(&self) -> f64{
+        if self.len == 0{
+            0.0
+        }
+        else{
+            self.holes() as f64 / self.len as f64
+        }
+    }
+}
+
+/// Components added and removed in a storage between two calls
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct StorageChurn{
+    pub component: &'static str,
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// How the world's entities are made up, from `World::composition`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CompositionStats{
+    /// Live entities, or every index any storage has a component at if there's no `EntityStorage`
+    pub entities: usize,
+    /// Most common first
+    pub archetypes: Vec<Archetype>,
+    /// In type name order
+    pub storages: Vec<StorageStats>,
+    /// Only there when made with `World::composition_since`
+    pub churn: Option<Vec<StorageChurn>>,
+    // Bitsets of the storages, in the same order
+    #[cfg_attr(feature = "serde", serde(skip))]
+    valid: Vec<(TypeId, BitVec)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    live: Option<Vec<Entity>>,
+}
+
+impl CompositionStats{
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String{
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    // Whether the index held the same entity in both stats, index reuse counts as a remove and an add
+    fn same_entity(&self, earlier: &CompositionStats, index: usize) -> bool{
+        match (&self.live, &earlier.live){
+            (Some(now), Some(before)) => {
+                let find = |live: &Vec<Entity>| live.binary_search_by_key(&index, |entity| entity.index).ok().map(|n| live[n]);
+                find(now) == find(before)
+            },
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for CompositionStats{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        writeln!(f, "{} entities, {} storages, {} archetypes", self.entities, self.storages.len(), self.archetypes.len())?;
+        writeln!(f, "archetypes:")?;
+        for archetype in self.archetypes.iter(){
+            let components = if archetype.components.is_empty() { "(none)".to_string() } else { archetype.components.join(", ") };
+            writeln!(f, "  {:>8}  {}", archetype.count, components)?;
+        }
+        writeln!(f, "storages:")?;
+        for storage in self.storages.iter(){
+            writeln!(f, "  {}: {}/{} occupied, {} holes ({:.1}%)",
+                storage.component, storage.occupied, storage.len, storage.holes(), storage.hole_ratio() * 100.0)?;
+        }
+        if let Some(churn) = &self.churn{
+            writeln!(f, "churn:")?;
+            for storage in churn.iter(){
+                writeln!(f, "  {}: +{} -{}", storage.component, storage.added, storage.removed)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl World{
+    /// Walks the validity bitsets of every storage to find which combinations of components entities have
+    /// and how full each storage is
+    pub fn composition(&self) -> CompositionStats{
+        let mut storages: Vec<(TypeId, &'static str, BitVec)> = self.storages()
+            .map(|(id, storage)|{
+                let storage = storage.read();
+                (*id, storage.component_name(), storage.valid().clone())
+            })
+            .collect();
+        storages.sort_by_key(|(_, name, _)| *name);
+
+        let live = if self.contains::<EntityStorage>(){
+            Some(self.get::<EntityStorage>().live_entities())
+        }
+        else{
+            None
+        };
+        let indices: Vec<usize> = match &live{
+            Some(live) => live.iter().map(|entity| entity.index).collect(),
+            None => {
+                let len = storages.iter().map(|(_, _, valid)| valid.len()).max().unwrap_or(0);
+                (0..len).filter(|index| storages.iter().any(|(_, _, valid)| valid.get(*index).unwrap_or(false))).collect()
+            },
+        };
+
+        let mut counts: HashMap<BitVec, usize> = HashMap::new();
+        for index in indices.iter(){
+            let signature: BitVec = storages.iter().map(|(_, _, valid)| valid.get(*index).unwrap_or(false)).collect();
+            *counts.entry(signature).or_insert(0) += 1;
+        }
+        let mut archetypes: Vec<Archetype> = counts.into_iter()
+            .map(|(signature, count)| Archetype{
+                components: signature.iter().zip(storages.iter()).filter(|(has, _)| *has).map(|(_, (_, name, _))| *name).collect(),
+                count,
+            })
+            .collect();
+        archetypes.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.components.cmp(&b.components)));
+
+        CompositionStats{
+            entities: indices.len(),
+            archetypes,
+            storages: storages.iter()
+                .map(|(_, name, valid)| StorageStats{
+                    component: name,
+                    occupied: valid.iter().filter(|bit| *bit).count(),
+                    len: valid.len(),
+                })
+                .collect(),
+            churn: None,
+            valid: storages.into_iter().map(|(id, _, valid)| (id, valid)).collect(),
+            live,
+        }
+    }
+
+    /// `composition` along with how many components were added and removed in each storage since `earlier`.
+    /// Only the difference is seen, a component removed and added back in between doesn't count.
+    pub fn composition_since(&self, earlier: &CompositionStats) -> CompositionStats{
+        let mut stats = self.composition();
+        let empty = BitVec::new();
+        let mut churn = Vec::new();
+        for (storage, (id, now)) in stats.storages.iter().zip(stats.valid.iter()){
+            let before = earlier.valid.iter().find(|(other, _)| other == id).map_or(&empty, |(_, valid)| valid);
+
+            let (mut added, mut removed) = (0, 0);
+            for index in 0..now.len().max(before.len()){
+                let (had, has) = (before.get(index).unwrap_or(false), now.get(index).unwrap_or(false));
+                if had && has && !stats.same_entity(earlier, index){
+                    added += 1;
+                    removed += 1;
+                }
+                else if has && !had{
+                    added += 1;
+                }
+                else if had && !has{
+                    removed += 1;
+                }
+            }
+            churn.push(StorageChurn{
+                component: storage.component,
+                added,
+                removed,
+            });
+        }
+        stats.churn = Some(churn);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Position(f32);
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Velocity(f32);
+
+    fn world() -> (World, Vec<Entity>){
+        let mut world = World::new();
+        world.insert(EntityStorage::new());
+        world.register_comp::<Position>();
+        world.register_comp::<Velocity>();
+        let entities: Vec<Entity> = (0..10).map(|n|{
+            let entity = *world.get_mut::<EntityStorage>().create_entity();
+            if n < 8{
+                world.get_comp_mut::<Position>().set(&entity.index, Position(n as f32));
+            }
+            if n % 2 == 0{
+                world.get_comp_mut::<Velocity>().set(&entity.index, Velocity(1.0));
+            }
+            entity
+        }).collect();
+        (world, entities)
+    }
+
+    #[test]
+    fn archetypes_and_holes(){
+        let (world, e) = world();
+        world.get_comp_mut::<Position>().delete(&e[0].index);
+        let stats = world.composition();
+
+        assert_eq!(stats.entities, 10);
+        let position = std::any::type_name::<Position>();
+        let velocity = std::any::type_name::<Velocity>();
+        assert_eq!(stats.archetypes, vec![
+            Archetype{components: vec![position], count: 4},
+            Archetype{components: vec![position, velocity], count: 3},
+            Archetype{components: vec![velocity], count: 2},
+            Archetype{components: vec![], count: 1},
+        ]);
+
+        let positions = stats.storages.iter().find(|storage| storage.component == position).unwrap();
+        assert_eq!((positions.occupied, positions.len, positions.holes()), (7, 8, 1));
+        assert!((positions.hole_ratio() - 0.125).abs() < 1e-9);
+        assert!(stats.churn.is_none());
+        assert!(stats.to_string().contains("7/8 occupied, 1 holes (12.5%)"));
+    }
+
+    #[test]
+    fn churn_between_calls(){
+        let (world, e) = world();
+        let before = world.composition();
+
+        world.get_comp_mut::<Velocity>().set(&e[1].index, Velocity(2.0));
+        world.get_comp_mut::<Velocity>().delete(&e[2].index);
+        world.get_comp_mut::<Velocity>().delete(&e[4].index);
+        // Despawning and spawning into the same index is a remove and an add even though the bit stays set
+        world.despawn(&e[6]);
+        let reused = *world.get_mut::<EntityStorage>().create_entity();
+        world.get_comp_mut::<Position>().set(&reused.index, Position(0.0));
+
+        let after = world.composition_since(&before);
+        let churn = after.churn.as_ref().unwrap();
+        let velocity = churn.iter().find(|storage| storage.component == std::any::type_name::<Velocity>()).unwrap();
+        assert_eq!((velocity.added, velocity.removed), (1, 3));
+        let position = churn.iter().find(|storage| storage.component == std::any::type_name::<Position>()).unwrap();
+        assert_eq!((position.added, position.removed), (1, 1));
+        assert!(after.to_string().contains("churn:"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json(){
+        let (world, _) = world();
+        let json: serde_json::Value = serde_json::from_str(&world.composition().to_json()).unwrap();
+        assert_eq!(json["entities"], 10);
+        assert_eq!(json["archetypes"][0]["count"], 4);
+        assert!(json["churn"].is_null());
+        assert!(json.get("valid").is_none());
+    }
+}
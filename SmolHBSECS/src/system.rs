@@ -3,22 +3,54 @@ use SmolCommon::system::*;
 use SmolCommon::component::Component;
 use SmolCommon::{DepVec, BitVec};
 use SmolCommon::{WorldCommon};
+use SmolCommon::profiler::{Profiler, SystemSpan};
 use std::sync::{Arc, Mutex, atomic::{Ordering, AtomicBool}};
 use std::collections::HashMap;
 use rayon;
 use std::ops::Deref;
+use std::time::Instant;
+use std::fmt::Write as FmtWrite;
+
+// What every system runs after once sets are taken into account
+type Dependencies = HashMap<String, Vec<String>>;
 
 // Stores systems as a tuple of dependencies, init funcs, and run funcs
 pub struct SystemScheduler<'d, 'w: 'd>{
     systems: HashMap<String, StoredSys<'d, 'w>>,
+    sets: HashMap<String, SystemSet>,
+    // Cleared whenever systems or sets change
+    resolved: Mutex<Option<Arc<Dependencies>>>,
     pool: Arc<rayon::ThreadPool>,
+    profiling: bool,
 }
 
 struct StoredSys<'d, 'w: 'd>{
     dep: Vec<String>,
+    sets: Vec<String>,
     system: Box<dyn SystemRunner<'d, 'w, World> + 'w>,
 }
 
+/// A named group of systems, ordering constraints between sets apply to every system in them
+#[derive(Default)]
+pub struct SystemSet{
+    before: Vec<String>,
+    after: Vec<String>,
+}
+
+impl SystemSet{
+    /// Every system in this set runs before every system in `set`
+    pub fn before(&mut self, set: &str) -> &mut Self{
+        self.before.push(set.to_string());
+        self
+    }
+
+    /// Every system in this set runs after every system in `set`
+    pub fn after(&mut self, set: &str) -> &mut Self{
+        self.after.push(set.to_string());
+        self
+    }
+}
+
 unsafe impl<'d, 'w: 'd> Send for StoredSys<'d, 'w>{}
 unsafe impl<'d, 'w: 'd> Sync for StoredSys<'d, 'w>{}
 
@@ -26,23 +58,187 @@ impl<'d, 'w: 'd> SystemScheduler<'d, 'w>{
     pub fn new(pool: Arc<rayon::ThreadPool>) -> Self{
         SystemScheduler{
             systems: HashMap::new(),
-            pool
+            sets: HashMap::new(),
+            resolved: Mutex::new(None),
+            pool,
+            profiling: false,
         }
     }
-}
 
-impl<'d, 'w: 'd> Scheduler<'d, 'w, World> for SystemScheduler<'d, 'w>{
+    /// Records every system run into the world's `Profiler` resource, which has to be inserted first
+    pub fn enable_profiling(&mut self){
+        self.profiling = true;
+    }
+
+    pub fn disable_profiling(&mut self){
+        self.profiling = false;
+    }
+
+    /// Adds a system that is a member of `set`, creating the set if it doesn't exist yet
+    pub fn add_to_set<M, S: IntoSystem<'d, 'w, World, M>>(&mut self, system: S, name: &str, dep: Vec<&str>, set: &str)
+        where S::System: 'w{
+        self.add(system, name, dep);
+        self.systems.get_mut(name).unwrap().sets.push(set.to_string());
+        self.sets.entry(set.to_string()).or_default();
+    }
+
+    /// Gets a set to add ordering constraints to, creating it if it doesn't exist yet.
+    /// `run` panics with the systems involved if the constraints contradict each other.
+    pub fn configure_set(&mut self, set: &str) -> &mut SystemSet{
+        *self.resolved.get_mut().unwrap() = None;
+        self.sets.entry(set.to_string()).or_default()
+    }
 
-    fn add<S:'w + System<'d, 'w, World>>(&mut self, system: S, name: &str, dep: Vec<&str>){
-        self.systems.insert(name.to_string(), 
+    /// Adds a system whose data isn't known until runtime, like one built from a config file.
+    /// The runner gets the whole world and reports what it touches through `get_system_dependencies`.
+    pub fn add_runner(&mut self, runner: Box<dyn SystemRunner<'d, 'w, World> + 'w>, name: &str, dep: Vec<&str>){
+        *self.resolved.get_mut().unwrap() = None;
+        self.systems.insert(name.to_string(),
             StoredSys{
                 dep: dep.iter().map(|s| s.to_string()).collect(),
-                system: Box::new(system),
+                sets: Vec::new(),
+                system: runner,
             });
     }
 
+    pub fn pool(&self) -> &Arc<rayon::ThreadPool>{
+        &self.pool
+    }
+
+    // Explicit dependencies plus every system in a set that one of our sets has to run after
+    fn resolve_dependencies(&self) -> HashMap<String, Vec<String>>{
+        let mut runs_after: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (name, set) in self.sets.iter(){
+            for after in set.after.iter(){
+                runs_after.entry(name.as_str()).or_default().push(after.as_str());
+            }
+            for before in set.before.iter(){
+                runs_after.entry(before.as_str()).or_default().push(name.as_str());
+            }
+        }
+
+        self.systems.iter().map(|(name, stored)|{
+            let mut dep = stored.dep.clone();
+            for set in stored.sets.iter(){
+                for after in runs_after.get(set.as_str()).into_iter().flatten(){
+                    for (other_name, other) in self.systems.iter(){
+                        if other_name != name && other.sets.iter().any(|s| s == after) && !dep.contains(other_name){
+                            dep.push(other_name.clone());
+                        }
+                    }
+                }
+            }
+            (name.clone(), dep)
+        }).collect()
+    }
+
+    // The resolved dependencies, worked out and checked for cycles the first time they're needed after a change.
+    // Panics naming the systems if they can't be put in an order.
+    fn resolved_dependencies(&self) -> Arc<Dependencies>{
+        if let Some(resolved) = self.resolved.lock().unwrap().as_ref(){
+            return resolved.clone();
+        }
+
+        let deps = self.resolve_dependencies();
+        let mut names: Vec<&String> = deps.keys().collect();
+        names.sort();
+        let mut finished = HashMap::new();
+        for name in names{
+            if let Some(cycle) = find_cycle(name, &deps, &mut finished, &mut Vec::new()){
+                panic!("Systems can't be ordered, {} runs after itself", cycle.join(" runs after "));
+            }
+        }
+
+        let deps = Arc::new(deps);
+        *self.resolved.lock().unwrap() = Some(deps.clone());
+        deps
+    }
+
+    /// Writes the system graph in Graphviz DOT format.
+    /// Solid edges point from a system to the systems that have to run after it,
+    /// dashed edges connect systems that use the same data and are labeled with the type they conflict on,
+    /// blue for components and orange for resources.
+    pub fn to_dot(&self, world: &World) -> String{
+        let mut names: Vec<&String> = self.systems.keys().collect();
+        names.sort();
+        let resolved_deps = self.resolved_dependencies();
+        let dep_vecs: HashMap<&String, DepVec> = self.systems.iter().map(|(key, value)| (key, value.system.get_system_dependencies(world))).collect();
+
+        let mut out = String::from("digraph systems{\n    node [shape=box];\n");
+        for name in names.iter(){
+            writeln!(out, "    \"{}\";", escape_dot(name)).unwrap();
+        }
+
+        for name in names.iter(){
+            let mut deps = resolved_deps.get(*name).unwrap().clone();
+            deps.sort();
+            for dep in deps.iter(){
+                writeln!(out, "    \"{}\" -> \"{}\";", escape_dot(dep), escape_dot(name)).unwrap();
+            }
+        }
+
+        for (n, first) in names.iter().enumerate(){
+            for second in names.iter().skip(n + 1){
+                let (res, comp) = dep_vecs.get(first).unwrap().conflicts(dep_vecs.get(second).unwrap());
+                let conflicts = res.iter().enumerate()
+                    .filter(|(_, conflict)| *conflict)
+                    .map(|(id, _)| (world.resource_name(id), "darkorange"))
+                    .chain(comp.iter().enumerate()
+                        .filter(|(_, conflict)| *conflict)
+                        .map(|(id, _)| (world.component_name(id), "blue")));
+
+                for (type_name, color) in conflicts{
+                    writeln!(out, "    \"{}\" -> \"{}\" [style=dashed, dir=none, color={}, label=\"{}\"];",
+                        escape_dot(first), escape_dot(second), color, escape_dot(type_name.unwrap_or("unknown"))).unwrap();
+                }
+            }
+        }
+
+        out.push_str("}\n");
+        out
+    }
+}
+
+// Depth first search from `name` along what each system runs after, `finished` holds false for the systems on `path`.
+// Returns the systems around the first cycle found, starting and ending with the same one.
+fn find_cycle<'a>(name: &'a str, deps: &'a HashMap<String, Vec<String>>, finished: &mut HashMap<&'a str, bool>, path: &mut Vec<&'a str>) -> Option<Vec<&'a str>>{
+    match finished.get(name){
+        Some(true) => return None,
+        Some(false) => {
+            let start = path.iter().position(|on_path| *on_path == name).unwrap();
+            let mut cycle = path[start..].to_vec();
+            cycle.push(name);
+            return Some(cycle);
+        },
+        None => (),
+    }
+
+    finished.insert(name, false);
+    path.push(name);
+    for dep in deps.get(name).into_iter().flatten(){
+        if let Some(cycle) = find_cycle(dep, deps, finished, path){
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    finished.insert(name, true);
+    None
+}
+
+fn escape_dot(s: &str) -> String{
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl<'d, 'w: 'd> Scheduler<'d, 'w, World> for SystemScheduler<'d, 'w>{
+
+    fn add<M, S: IntoSystem<'d, 'w, World, M>>(&mut self, system: S, name: &str, dep: Vec<&str>)
+        where S::System: 'w{
+        self.add_runner(Box::new(system.into_system()), name, dep);
+    }
+
     fn run(&self, world: &'w World){
 
+        let resolved_deps = self.resolved_dependencies();
         let systems_done: HashMap<String, Arc<AtomicBool>> = self.systems.iter().map(|(key, _)| (key.clone(), Arc::new(AtomicBool::from(false)))).collect();
         let dep_vecs: HashMap<String, DepVec> = self.systems.iter().map(|(key, value)| (key.clone(), value.system.get_system_dependencies(&world))).collect();
         let in_use_resources: Arc<Mutex<HashMap<String, DepVec>>> = Arc::new(Mutex::new(HashMap::new()));
@@ -66,7 +262,7 @@ impl<'d, 'w: 'd> Scheduler<'d, 'w, World> for SystemScheduler<'d, 'w>{
                 systems_done_check = false;
 
                 //When incomplete system found, check if it's dependencies are complete
-                let sys_dep = &self.systems.get(sys).unwrap().dep;
+                let sys_dep = resolved_deps.get(sys).unwrap();
                 match sys_dep.iter().find(|dependency| systems_done.get(*dependency).unwrap().load(Ordering::Relaxed) == false){
                     
                     //If dependencies aren't complete, continue checking systems
@@ -97,7 +293,23 @@ impl<'d, 'w: 'd> Scheduler<'d, 'w, World> for SystemScheduler<'d, 'w>{
                 let system_to_run = &self.systems.get(&sys_clone);
 
                 self.pool.scope_fifo(|s|{
-                    system_to_run.as_ref().unwrap().system.get_and_run(&world);
+                    let system = &system_to_run.as_ref().unwrap().system;
+                    if self.profiling{
+                        let start = Instant::now();
+                        let (acquired, end) = system.get_and_run_timed(&world);
+                        let mut profiler = world.get_mut::<Profiler>();
+                        let span = SystemSpan{
+                            system: sys_clone.clone(),
+                            thread: rayon::current_thread_index(),
+                            start: profiler.since_epoch(start),
+                            acquired: profiler.since_epoch(acquired),
+                            end: profiler.since_epoch(end),
+                        };
+                        profiler.record(span);
+                    }
+                    else{
+                        system.get_and_run(&world);
+                    }
                     in_use_clone.lock().unwrap().remove(&sys_clone);
                     done_clone.store(true, Ordering::Relaxed);
                 });
@@ -106,6 +318,12 @@ impl<'d, 'w: 'd> Scheduler<'d, 'w, World> for SystemScheduler<'d, 'w>{
             all_systems_done = systems_done_check;
         }
     }
+
+    fn setup(&self, world: &mut World){
+        for (_, stored) in self.systems.iter(){
+            stored.system.setup(world);
+        }
+    }
 }
 #[cfg(test)]
 mod tests{
@@ -114,7 +332,9 @@ mod tests{
     use SmolCommon::WorldCommon;
     use SmolCommon::system::*;
     use SmolCommon::join::Joinable;
-    use std::convert::TryInto;
+    use std::convert::TryFrom;
+    use SmolCommon::{AccessType, DepVec};
+    use std::time::Instant;
 
     #[test]
     fn read(){
@@ -195,7 +415,8 @@ mod tests{
         let reader_isize = ReadComp::<isize>::get_data(&world);
 
         for (u, i) in (&reader_usize, &reader_isize).join(){
-            assert_eq!(*u, (-i).try_into().unwrap());
+            // serde_json adds `PartialEq<Value> for usize`, so `try_into` can't infer its target with the serde feature
+            assert_eq!(*u, usize::try_from(-i).unwrap());
         }
     }
 
@@ -219,7 +440,8 @@ mod tests{
         let reader_u8 = ReadComp::<u8>::get_data(&world);
 
         for (u, i, smol_u) in (&reader_usize, &reader_isize, &reader_u8).join(){
-            assert_eq!(*u, (-i).try_into().unwrap());
+            // serde_json adds `PartialEq<Value> for usize`, so `try_into` can't infer its target with the serde feature
+            assert_eq!(*u, usize::try_from(-i).unwrap());
             assert_eq!(*u, *smol_u as usize);
         }
     }
@@ -341,6 +563,26 @@ mod tests{
         }
     }
 
+    #[test]
+    fn tuple_writes_conflict(){
+        let mut world = World::new();
+        world.register_comp::<usize>();
+        world.register_comp::<isize>();
+        world.register_comp::<u32>();
+
+        // The scheduler won't start a system whose writes intersect what a running system uses.
+        // Tuples used to combine their DepVecs with `and`, which dropped any access the parts didn't share.
+        let two = <TimesTwo as System<World>>::SystemData::get_dep_vec(&world);
+        let three = <TimesThree as System<World>>::SystemData::get_dep_vec(&world);
+        assert!(two.intersection(three.res_write.clone(), three.comp_write.clone()));
+        assert!(three.intersection(two.res_write.clone(), two.comp_write.clone()));
+
+        let readers = <(ReadComp<usize>, ReadComp<isize>)>::get_dep_vec(&world);
+        let other = <(ReadComp<isize>, WriteComp<u32>)>::get_dep_vec(&world);
+        assert!(!readers.intersection(other.res_write.clone(), other.comp_write.clone()));
+        assert!(!other.intersection(readers.res_write.clone(), readers.comp_write.clone()));
+    }
+
     #[test]
     fn scheduler_double_write_test(){
         let mut world = World::new();
@@ -373,4 +615,301 @@ mod tests{
             assert_eq!(i * 3, num);
         }
     }
-}
\ No newline at end of file
+
+    struct Log(Vec<&'static str>);
+
+    struct Physics;
+
+    impl<'d, 'w: 'd> System<'d, 'w, World> for Physics{
+        type SystemData = Write<'d, Log>;
+
+        fn run(&self, mut log: Self::SystemData){
+            log.0.push("physics");
+        }
+    }
+
+    struct Collide;
+
+    impl<'d, 'w: 'd> System<'d, 'w, World> for Collide{
+        type SystemData = Write<'d, Log>;
+
+        fn run(&self, mut log: Self::SystemData){
+            log.0.push("collide");
+        }
+    }
+
+    struct Render;
+
+    impl<'d, 'w: 'd> System<'d, 'w, World> for Render{
+        type SystemData = Write<'d, Log>;
+
+        fn run(&self, mut log: Self::SystemData){
+            log.0.push("render");
+        }
+    }
+
+    struct Ui;
+
+    impl<'d, 'w: 'd> System<'d, 'w, World> for Ui{
+        type SystemData = Write<'d, Log>;
+
+        fn run(&self, mut log: Self::SystemData){
+            log.0.push("ui");
+        }
+    }
+
+    #[test]
+    fn set_ordering(){
+        let mut world = World::new();
+        world.insert(Log(Vec::new()));
+
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(8).build().unwrap());
+
+        let mut scheduler = SystemScheduler::new(pool);
+        // Added in the opposite order so only the set ordering can put them right
+        scheduler.add_to_set(Ui{}, "ui", Vec::new(), "ui");
+        scheduler.add_to_set(Render{}, "render", Vec::new(), "render");
+        scheduler.add_to_set(Collide{}, "collide", vec!["physics"], "physics");
+        scheduler.add_to_set(Physics{}, "physics", Vec::new(), "physics");
+        scheduler.configure_set("physics").before("render");
+        scheduler.configure_set("ui").after("render");
+
+        for _ in 0..20{
+            scheduler.run(&world);
+        }
+
+        let log = Read::<Log>::get_data(&world);
+        assert_eq!(log.0.len(), 20 * 4);
+        for frame in log.0.chunks(4){
+            assert_eq!(frame, &["physics", "collide", "render", "ui"]);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "runs after itself")]
+    fn set_cycle(){
+        let mut world = World::new();
+        world.insert(Log(Vec::new()));
+
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+        let mut scheduler = SystemScheduler::new(pool);
+        scheduler.add_to_set(Physics{}, "physics", Vec::new(), "physics");
+        scheduler.add_to_set(Render{}, "render", Vec::new(), "render");
+        scheduler.configure_set("physics").before("render");
+        scheduler.configure_set("physics").after("render");
+
+        // Would wait forever for a system that can never start
+        scheduler.run(&world);
+    }
+
+    #[test]
+    fn profiler_records_systems(){
+        use SmolCommon::profiler::Profiler;
+
+        let mut world = World::new();
+        world.register_comp::<usize>();
+        world.register_comp::<isize>();
+        world.insert(Profiler::new(4, 16));
+
+        for i in 0..10{
+            world.get_comp_mut::<usize>().set(&i, i);
+            world.get_comp_mut::<isize>().set(&i, i as isize);
+        }
+
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+
+        let mut scheduler = SystemScheduler::new(pool);
+        scheduler.add(TimesTwo{}, "times_two", Vec::new());
+        scheduler.enable_profiling();
+
+        for _ in 0..6{
+            scheduler.run(&world);
+        }
+
+        let profiler = world.get::<Profiler>();
+        let stats = profiler.stats("times_two").unwrap();
+        assert_eq!(stats.runs(), 6);
+        assert!(stats.min() <= stats.mean() && stats.mean() <= stats.max());
+        assert_eq!(profiler.spans().count(), 6);
+
+        let trace = profiler.chrome_trace();
+        assert!(trace.starts_with("{\"traceEvents\":[{\"name\":\"times_two\""));
+        assert_eq!(trace.matches("\"ph\":\"X\"").count(), 6);
+    }
+
+    #[test]
+    fn dot_export(){
+        let mut world = World::new();
+
+        world.register_comp::<usize>();
+        world.register_comp::<isize>();
+        world.register_comp::<u32>();
+
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+
+        let mut scheduler = SystemScheduler::new(pool);
+        scheduler.add(TimesTwo{}, "times_two", Vec::new());
+        scheduler.add(TimesThree{}, "times_three", vec!["times_two"]);
+
+        let dot = scheduler.to_dot(&world);
+        assert!(dot.starts_with("digraph systems{"));
+        assert!(dot.contains("\"times_two\" -> \"times_three\";"));
+        assert!(dot.contains("\"times_three\" -> \"times_two\" [style=dashed, dir=none, color=blue, label=\"usize\"];"));
+        // Both only read isize, so it isn't a conflict
+        assert!(!dot.contains("label=\"isize\""));
+    }
+
+    #[derive(Default)]
+    struct Frames(usize);
+
+    struct CountFrames;
+
+    impl<'d, 'w: 'd> System<'d, 'w, World> for CountFrames{
+        // Log is inserted by the test after setup, so it opts out of the check plain Write does
+        type SystemData = (WriteDefault<'d, Frames>, Write<'d, Log, NoSetup>);
+
+        fn run(&self, (mut frames, mut log): Self::SystemData){
+            frames.0 += 1;
+            log.0.push("count");
+        }
+    }
+
+    #[test]
+    fn setup_registers_data(){
+        let mut world = World::new();
+
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+
+        let mut scheduler = SystemScheduler::new(pool);
+        scheduler.add(TimesThree{}, "times_three", Vec::new());
+        scheduler.add(CountFrames{}, "count", Vec::new());
+        scheduler.setup(&mut world);
+
+        assert!(world.contains_comp::<usize>());
+        assert!(world.contains_comp::<isize>());
+        assert!(world.contains_comp::<u32>());
+        // Only resources that opted into a default get inserted
+        assert!(world.contains::<Frames>());
+        assert!(!world.contains::<Log>());
+
+        world.insert(Log(Vec::new()));
+        scheduler.run(&world);
+        assert_eq!(Read::<Frames>::get_data(&world).0, 1);
+        assert_eq!(Read::<Log>::get_data(&world).0, vec!["count"]);
+    }
+
+    struct Greet;
+
+    impl<'d, 'w: 'd> System<'d, 'w, World> for Greet{
+        type SystemData = ReadExpect<'d, String>;
+
+        fn run(&self, _greeting: Self::SystemData){}
+    }
+
+    #[test]
+    #[should_panic]
+    fn setup_expects_resource(){
+        let mut world = World::new();
+
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+
+        let mut scheduler = SystemScheduler::new(pool);
+        scheduler.add(Greet{}, "greet", Vec::new());
+        scheduler.setup(&mut world);
+    }
+
+    #[test]
+    #[should_panic(expected = "Log has to be inserted")]
+    fn setup_names_missing_resource(){
+        let mut world = World::new();
+
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+
+        let mut scheduler = SystemScheduler::new(pool);
+        scheduler.add(Render{}, "render", Vec::new());
+        scheduler.setup(&mut world);
+    }
+
+    fn double(mut nums: WriteComp<usize>, _others: ReadComp<isize>){
+        for num in (&mut nums).join(){
+            *num *= 2;
+        }
+    }
+
+    #[test]
+    fn function_systems(){
+        let mut world = World::new();
+
+        world.register_comp::<usize>();
+        world.register_comp::<isize>();
+        world.insert(Log(Vec::new()));
+
+        for i in 0..10{
+            world.get_comp_mut::<usize>().set(&i, i);
+            world.get_comp_mut::<isize>().set(&i, i as isize);
+        }
+
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+
+        let mut scheduler = SystemScheduler::new(pool);
+        scheduler.add(double, "double", Vec::new());
+        scheduler.add(|mut log: Write<Log>| log.0.push("closure"), "closure", vec!["double"]);
+        scheduler.add(|_nums: ReadComp<usize>, _log: Read<Log>|{}, "reader", vec!["closure"]);
+
+        scheduler.run(&world);
+
+        for (i, &num) in (&ReadComp::<usize>::get_data(&world)).join().enumerate(){
+            assert_eq!(i * 2, num);
+        }
+        assert_eq!(Read::<Log>::get_data(&world).0, vec!["closure"]);
+
+        // Dependencies come from the parameter types
+        let dot = scheduler.to_dot(&world);
+        assert!(dot.contains("\"double\" -> \"reader\" [style=dashed, dir=none, color=blue, label=\"usize\"];"));
+        assert!(dot.contains("\"closure\" -> \"reader\" [style=dashed, dir=none, color=darkorange"));
+    }
+
+    // Doubles whichever storage it was built for
+    struct Doubler{
+        double: fn(&World),
+        deps: fn(&World) -> DepVec,
+    }
+
+    impl<'d, 'w: 'd> SystemRunner<'d, 'w, World> for Doubler{
+        fn get_and_run(&self, world: &'w World){
+            (self.double)(world);
+        }
+
+        fn get_and_run_timed(&self, world: &'w World) -> (Instant, Instant){
+            let acquired = Instant::now();
+            self.get_and_run(world);
+            (acquired, Instant::now())
+        }
+
+        fn get_system_dependencies(&self, world: &World) -> DepVec{
+            (self.deps)(world)
+        }
+
+        fn setup(&self, _world: &mut World){}
+    }
+
+    #[test]
+    fn runtime_systems(){
+        let mut world = World::new();
+        world.register_comp::<usize>();
+        world.register_comp::<isize>();
+        world.get_comp_mut::<usize>().set(&0, 3);
+
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+        let mut scheduler = SystemScheduler::new(pool);
+        scheduler.add_runner(Box::new(Doubler{
+            double: |world| *world.get_comp_mut::<usize>().get_mut(&0).unwrap() *= 2,
+            deps: |world| world.get_dep_vec_comp::<usize>(AccessType::Write),
+        }), "double", Vec::new());
+        scheduler.add(|_nums: ReadComp<usize>|{}, "reader", Vec::new());
+        scheduler.run(&world);
+
+        assert_eq!(*world.get_comp::<usize>().get(&0).unwrap(), 6);
+        assert!(scheduler.to_dot(&world).contains("[style=dashed, dir=none, color=blue, label=\"usize\"]"));
+    }
+}
@@ -0,0 +1,193 @@
+use crate::world::World;
+use crate::{Entity, EntityStorage};
+use crate::entity_map::EntityMap;
+use crate::hierarchy::{Parent, Children};
+use SmolCommon::WorldCommon;
+use std::collections::HashSet;
+
+impl World{
+    /// Copies an entity and every component it has into another world, returning the new entity.
+    /// Storages the destination doesn't have yet are registered. See `clone_entities_to`.
+    pub fn clone_entity_to(&self, entity: &Entity, dest: &mut World) -> Entity{
+        self.clone_entities_to(&[*entity], dest).map(entity)
+    }
+
+    /// Moves an entity and every component it has into another world, deleting it here.
+    /// Its children stay in this world and become roots.
+    pub fn move_entity_to(&self, entity: &Entity, dest: &mut World) -> Entity{
+        self.move_entities_to(&[*entity], dest).map(entity)
+    }
+
+    /// Copies a set of entities into another world, returning which new entity each one became.
+    /// Parent/child links between entities in the set are rebuilt in the destination, links to entities
+    /// outside of it are dropped. Other components holding entities can be fixed up with `TypeRegistry::map_entities`.
+    /// Entities that are in the set more than once are copied once. Panics if an entity isn't alive.
+    pub fn clone_entities_to(&self, entities: &[Entity], dest: &mut World) -> EntityMap{
+        let entities = &unique(entities);
+        self.register_storages_in(dest);
+
+        let mut map = EntityMap::new();
+        {
+            let alive = self.get::<EntityStorage>();
+            let mut dest_entities = dest.get_mut::<EntityStorage>();
+            for entity in entities{
+                assert!(alive.is_alive(entity), "Entity {} isn't alive", entity.index());
+                map.insert(*entity, *dest_entities.create_entity());
+            }
+        }
+
+        self.copy_components_to(entities, dest, &map);
+        self.rebuild_hierarchy_in(entities, dest, &map);
+        map
+    }
+
+    /// Registers every storage this world has and an EntityStorage in another world, if they're missing
+    pub(crate) fn register_storages_in(&self, dest: &mut World){
+        if !dest.contains::<EntityStorage>(){
+            dest.insert(EntityStorage::new());
+        }
+        for (id, storage) in self.storages(){
+            if !dest.contains_comp_id(*id){
+                dest.register_storage(*id, storage.read().empty());
+            }
+        }
+    }
+
+    /// Copies every component of the entities to the entities they're mapped to in another world
+    pub(crate) fn copy_components_to(&self, entities: &[Entity], dest: &World, map: &EntityMap){
+        for (id, storage) in self.storages(){
+            let storage = storage.read();
+            let mut dest_storage = dest.storage(id).unwrap().write();
+            for entity in entities{
+                storage.copy_index(entity.index(), &mut **dest_storage, map.map(entity).index());
+            }
+        }
+    }
+
+    /// Replaces the copied hierarchy links of the mapped entities with the links between them in this world
+    pub(crate) fn rebuild_hierarchy_in(&self, entities: &[Entity], dest: &World, map: &EntityMap){
+        if !self.has_hierarchy(){
+            return;
+        }
+        for entity in entities{
+            let new = map.map(entity);
+            dest.get_comp_mut::<Parent>().delete(&new.index());
+            dest.get_comp_mut::<Children>().delete(&new.index());
+        }
+        for entity in entities{
+            for child in self.children(entity){
+                if let Some(new_child) = map.get(&child){
+                    dest.set_parent(&new_child, &map.map(entity));
+                }
+            }
+        }
+    }
+
+    /// Moves a set of entities into another world, deleting them here, see `clone_entities_to`.
+    /// Children that aren't in the set stay in this world and become roots.
+    pub fn move_entities_to(&self, entities: &[Entity], dest: &mut World) -> EntityMap{
+        let entities = &unique(entities);
+        let map = self.clone_entities_to(entities, dest);
+        if self.has_hierarchy(){
+            for entity in entities{
+                self.remove_parent(entity);
+                for child in self.children(entity){
+                    self.remove_parent(&child);
+                }
+            }
+        }
+        // Not despawn, that would take the children that stayed with it
+        self.delete_entities(entities);
+        map
+    }
+}
+
+// The entities without repeats, in the order they first show up
+fn unique(entities: &[Entity]) -> Vec<Entity>{
+    let mut seen = HashSet::new();
+    entities.iter().filter(|entity| seen.insert(**entity)).copied().collect()
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Name(&'static str);
+
+    fn world() -> (World, Vec<Entity>){
+        let mut world = World::new();
+        world.insert(EntityStorage::new());
+        world.register_hierarchy();
+        world.register_comp::<Name>();
+        world.register_comp::<u32>();
+        let names = ["root", "child", "grandchild", "other"];
+        let entities: Vec<Entity> = names.iter().enumerate().map(|(n, name)|{
+            let entity = *world.get_mut::<EntityStorage>().create_entity();
+            world.get_comp_mut::<Name>().set(&entity.index(), Name(name));
+            if n % 2 == 0{
+                world.get_comp_mut::<u32>().set(&entity.index(), n as u32);
+            }
+            entity
+        }).collect();
+        world.set_parent(&entities[1], &entities[0]);
+        world.set_parent(&entities[2], &entities[1]);
+        (world, entities)
+    }
+
+    #[test]
+    fn move_one(){
+        let (world, e) = world();
+        let mut dest = World::new();
+        dest.insert(EntityStorage::new());
+        dest.get_mut::<EntityStorage>().create_entity();
+
+        let moved = world.move_entity_to(&e[2], &mut dest);
+        assert_eq!(moved.index(), 1);
+        assert_eq!(*dest.get_comp::<Name>().get(&1).unwrap(), Name("grandchild"));
+        assert_eq!(*dest.get_comp::<u32>().get(&1).unwrap(), 2);
+        assert!(dest.parent(&moved).is_none());
+
+        assert!(!world.get::<EntityStorage>().is_alive(&e[2]));
+        assert!(world.children(&e[1]).is_empty());
+    }
+
+    #[test]
+    fn move_parent_without_child(){
+        let (world, e) = world();
+        let mut dest = World::new();
+
+        let map = world.move_entities_to(&[e[1], e[1]], &mut dest);
+        assert_eq!(map.len(), 1);
+        assert_eq!(dest.get::<EntityStorage>().live_entities(), vec![map.map(&e[1])]);
+        assert!(dest.children(&map.map(&e[1])).is_empty());
+
+        // The grandchild wasn't moved, it stays here as a root
+        let entities = world.get::<EntityStorage>();
+        assert!(!entities.is_alive(&e[1]) && entities.is_alive(&e[2]));
+        assert!(world.parent(&e[2]).is_none());
+        assert!(world.children(&e[0]).is_empty());
+        assert_eq!(*world.get_comp::<Name>().get(&e[2].index()).unwrap(), Name("grandchild"));
+    }
+
+    #[test]
+    fn clone_set(){
+        let (world, e) = world();
+        let mut dest = World::new();
+
+        let map = world.clone_entities_to(&[e[1], e[2], e[3]], &mut dest);
+        assert_eq!(map.len(), 3);
+        assert_eq!(dest.get::<EntityStorage>().live_entities().len(), 3);
+
+        let child = map.map(&e[1]);
+        let grandchild = map.map(&e[2]);
+        assert!(dest.parent(&child).is_none());
+        assert!(dest.children(&child) == vec![grandchild]);
+        assert_eq!(*dest.get_comp::<Name>().get(&map.map(&e[3]).index()).unwrap(), Name("other"));
+        assert!(dest.get_comp::<u32>().get(&child.index()).is_none());
+
+        // The source is untouched
+        assert_eq!(world.get::<EntityStorage>().live_entities().len(), 4);
+        assert!(world.parent(&e[1]) == Some(e[0]));
+    }
+}
@@ -0,0 +1,258 @@
+use crate::world::World;
+use crate::hierarchy::{Parent, Children};
+use SmolCommon::system::{System, ReadComp, WriteComp};
+use SmolCommon::join::Joinable;
+use rayon::prelude::*;
+
+/// Translation, rotation as a unit quaternion (x, y, z, w) and uniform scale
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Transform{
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: f32,
+}
+
+impl Default for Transform{
+    fn default() -> Self{
+        Transform::IDENTITY
+    }
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3]{
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+impl Transform{
+    pub const IDENTITY: Transform = Transform{
+        translation: [0.0; 3],
+        rotation: [0.0, 0.0, 0.0, 1.0],
+        scale: 1.0,
+    };
+
+    pub fn from_translation(translation: [f32; 3]) -> Self{
+        Transform{
+            translation,
+            ..Transform::IDENTITY
+        }
+    }
+
+    /// Rotation around the z axis in radians
+    pub fn from_rotation_z(angle: f32) -> Self{
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        Transform{
+            rotation: [0.0, 0.0, sin, cos],
+            ..Transform::IDENTITY
+        }
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self{
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_translation(mut self, translation: [f32; 3]) -> Self{
+        self.translation = translation;
+        self
+    }
+
+    pub fn rotate(&self, v: [f32; 3]) -> [f32; 3]{
+        let q = [self.rotation[0], self.rotation[1], self.rotation[2]];
+        let w = self.rotation[3];
+        let t = cross(q, v);
+        let t = [2.0 * t[0], 2.0 * t[1], 2.0 * t[2]];
+        let u = cross(q, t);
+        [v[0] + w * t[0] + u[0], v[1] + w * t[1] + u[1], v[2] + w * t[2] + u[2]]
+    }
+
+    pub fn transform_point(&self, point: [f32; 3]) -> [f32; 3]{
+        let p = self.rotate([point[0] * self.scale, point[1] * self.scale, point[2] * self.scale]);
+        [p[0] + self.translation[0], p[1] + self.translation[1], p[2] + self.translation[2]]
+    }
+
+    /// This transform applied after `child`, how a child's local transform becomes global
+    pub fn mul_transform(&self, child: &Transform) -> Transform{
+        let [ax, ay, az, aw] = self.rotation;
+        let [bx, by, bz, bw] = child.rotation;
+        Transform{
+            translation: self.transform_point(child.translation),
+            rotation: [
+                aw * bx + ax * bw + ay * bz - az * by,
+                aw * by - ax * bz + ay * bw + az * bx,
+                aw * bz + ax * by - ay * bx + az * bw,
+                aw * bw - ax * bx - ay * by - az * bz,
+            ],
+            scale: self.scale * child.scale,
+        }
+    }
+}
+
+/// Transform relative to the parent, or to the world for roots
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LocalTransform(pub Transform);
+
+/// Transform relative to the world, written by `TransformPropagation`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GlobalTransform(pub Transform);
+
+/// Computes every `GlobalTransform` from the `LocalTransform`s top down.
+/// Each root's subtree is walked as its own rayon task, in the scheduler's pool when run by a `SystemScheduler`.
+/// Children without a `LocalTransform` are treated as having the identity transform, and a `GlobalTransform`
+/// on an entity that isn't a root with a `LocalTransform` or under one is deleted.
+pub struct TransformPropagation;
+
+// What the walk needs of one index, storages can't be shared between threads so it's copied out first
+#[derive(Clone, Copy, Default)]
+struct Node{
+    local: Option<Transform>,
+    has_parent: bool,
+    first_child: Option<usize>,
+    next_sibling: Option<usize>,
+}
+
+fn node(nodes: &mut Vec<Node>, index: usize) -> &mut Node{
+    if nodes.len() <= index{
+        nodes.resize(index + 1, Node::default());
+    }
+    &mut nodes[index]
+}
+
+impl<'d, 'w: 'd> System<'d, 'w, World> for TransformPropagation{
+    type SystemData = (
+        ReadComp<'d, LocalTransform>,
+        ReadComp<'d, Parent>,
+        ReadComp<'d, Children>,
+        WriteComp<'d, GlobalTransform>,
+    );
+
+    fn run(&self, (locals, parents, children, mut globals): Self::SystemData){
+        let mut nodes = Vec::new();
+        for (index, (valid, local)) in (&locals).join().items.enumerate(){
+            if let (true, Some(local)) = (valid, local){
+                node(&mut nodes, index).local = Some(local.0);
+            }
+        }
+        for (index, (valid, parent)) in (&parents).join().items.enumerate(){
+            if let (true, Some(parent)) = (valid, parent){
+                let node = node(&mut nodes, index);
+                node.has_parent = true;
+                node.next_sibling = parent.next_sibling().map(|sibling| sibling.index());
+            }
+        }
+        for (index, (valid, children)) in (&children).join().items.enumerate(){
+            if let (true, Some(children)) = (valid, children){
+                node(&mut nodes, index).first_child = children.first().map(|child| child.index());
+            }
+        }
+
+        let roots: Vec<(usize, Transform)> = nodes.iter()
+            .enumerate()
+            .filter(|(_, node)| !node.has_parent)
+            .filter_map(|(index, node)| node.local.map(|local| (index, local)))
+            .collect();
+
+        let subtrees: Vec<Vec<(usize, Transform)>> = roots.par_iter()
+            .map(|&root|{
+                let mut computed = Vec::new();
+                let mut stack = vec![root];
+                while let Some((index, global)) = stack.pop(){
+                    computed.push((index, global));
+                    let mut next = nodes[index].first_child;
+                    while let Some(child) = next{
+                        let local = nodes[child].local.unwrap_or_default();
+                        stack.push((child, global.mul_transform(&local)));
+                        next = nodes[child].next_sibling;
+                    }
+                }
+                computed
+            })
+            .collect();
+
+        let mut reached = vec![false; nodes.len()];
+        for (index, global) in subtrees.into_iter().flatten(){
+            reached[index] = true;
+            globals.set(index, GlobalTransform(global));
+        }
+        let stale: Vec<usize> = (&globals).join().items
+            .enumerate()
+            .filter(|(index, (valid, _))| *valid && !reached.get(*index).copied().unwrap_or(false))
+            .map(|(index, _)| index)
+            .collect();
+        for index in stale{
+            globals.delete(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::{Entity, EntityStorage};
+    use crate::system::SystemScheduler;
+    use SmolCommon::WorldCommon;
+    use SmolCommon::system::Scheduler;
+    use std::sync::Arc;
+
+    fn close(a: [f32; 3], b: [f32; 3]) -> bool{
+        a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < 1e-5)
+    }
+
+    #[test]
+    fn compose(){
+        let parent = Transform::from_rotation_z(std::f32::consts::FRAC_PI_2).with_translation([1.0, 0.0, 0.0]).with_scale(2.0);
+        let child = Transform::from_translation([1.0, 0.0, 0.0]);
+        let global = parent.mul_transform(&child);
+        assert!(close(global.translation, [1.0, 2.0, 0.0]));
+        assert!(close(global.transform_point([1.0, 0.0, 0.0]), [1.0, 4.0, 0.0]));
+    }
+
+    #[test]
+    fn propagate(){
+        let mut world = World::new();
+        world.insert(EntityStorage::new());
+
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap());
+        let mut scheduler = SystemScheduler::new(pool);
+        scheduler.add(TransformPropagation, "propagate", vec![]);
+        scheduler.setup(&mut world);
+
+        let spawn = |world: &World, translation: [f32; 3]|{
+            let entity = *world.get_mut::<EntityStorage>().create_entity();
+            world.get_comp_mut::<LocalTransform>().set(&entity.index(), LocalTransform(Transform::from_translation(translation)));
+            entity
+        };
+
+        let roots: Vec<Entity> = (0..8).map(|n| spawn(&world, [n as f32 * 10.0, 0.0, 0.0])).collect();
+        let mut leaves = Vec::new();
+        for root in roots.iter(){
+            let child = spawn(&world, [0.0, 1.0, 0.0]);
+            let leaf = spawn(&world, [0.0, 0.0, 1.0]);
+            world.set_parent(&child, root);
+            world.set_parent(&leaf, &child);
+            leaves.push(leaf);
+        }
+        scheduler.run(&world);
+
+        for (n, leaf) in leaves.iter().enumerate(){
+            let global = world.get_comp::<GlobalTransform>().get(&leaf.index()).unwrap().0;
+            assert!(close(global.translation, [n as f32 * 10.0, 1.0, 1.0]));
+        }
+
+        // Moving a subtree moves it along with its new parent
+        let child = world.parent(&leaves[0]).unwrap();
+        world.set_parent(&child, &roots[3]);
+        world.get_comp_mut::<LocalTransform>().get_mut(&roots[3].index()).unwrap().0.scale = 2.0;
+        scheduler.run(&world);
+        let global = world.get_comp::<GlobalTransform>().get(&leaves[0].index()).unwrap().0;
+        assert!(close(global.translation, [30.0, 2.0, 2.0]));
+
+        // Without a LocalTransform a root is no longer propagated, so its subtree's globals go
+        world.get_comp_mut::<LocalTransform>().delete(&roots[5].index());
+        scheduler.run(&world);
+        let globals = world.get_comp::<GlobalTransform>();
+        assert!(globals.get(&roots[5].index()).is_none() && globals.get(&leaves[5].index()).is_none());
+        assert!(globals.get(&leaves[6].index()).is_some());
+    }
+}
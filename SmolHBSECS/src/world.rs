@@ -2,8 +2,9 @@ use SmolCommon::{WorldCommon, Resource, DepVec, AccessType, BitVec};
 use SmolCommon::component::{Component, ComponentStorage};
 use std::collections::HashMap;
 use std::any::{Any, TypeId};
+use std::alloc::Layout;
 use std::cell::{RefCell, Ref, RefMut};
-use crate::component::VecStorage;
+use crate::component::{VecStorage, AnyStorage};
 
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard, MappedRwLockReadGuard, MappedRwLockWriteGuard};
 use std::borrow::{Borrow, BorrowMut};
@@ -11,8 +12,11 @@ use std::borrow::{Borrow, BorrowMut};
 pub struct World{
     resource_ids: HashMap<TypeId, usize>,
     component_ids: HashMap<TypeId, usize>,
+    resource_names: Vec<&'static str>,
+    component_names: Vec<&'static str>,
+    resource_layouts: HashMap<TypeId, Layout>,
     resources: HashMap<TypeId, RwLock<Box<dyn Any>>>,
-    components: HashMap<TypeId, RwLock<Box<dyn Any>>>
+    components: HashMap<TypeId, RwLock<Box<dyn AnyStorage>>>
 }
 
 unsafe impl Send for World{}
@@ -23,45 +27,137 @@ impl World{
         World{
             resource_ids: HashMap::new(),
             component_ids: HashMap::new(),
+            resource_names: Vec::new(),
+            component_names: Vec::new(),
+            resource_layouts: HashMap::new(),
             resources: HashMap::new(),
             components: HashMap::new()
         }
     }
+
+    pub(crate) fn contains_comp_id(&self, id: TypeId) -> bool{
+        self.components.contains_key(&id)
+    }
+
+    /// Every storage, type erased
+    pub(crate) fn storages(&self) -> impl Iterator<Item = (&TypeId, &RwLock<Box<dyn AnyStorage>>)>{
+        self.components.iter()
+    }
+
+    /// Every resource with its type name and layout
+    pub(crate) fn resource_entries(&self) -> impl Iterator<Item = (&TypeId, &'static str, Layout)>{
+        self.resources.keys().map(move |id| (id, self.resource_names[self.resource_ids[id]], self.resource_layouts[id]))
+    }
+
+    pub(crate) fn storage(&self, id: &TypeId) -> Option<&RwLock<Box<dyn AnyStorage>>>{
+        self.components.get(id)
+    }
+
+    /// Registers a type erased storage, like `register_comp` does for a known type
+    pub(crate) fn register_storage(&mut self, id: TypeId, storage: Box<dyn AnyStorage>){
+        let next = self.component_ids.len();
+        let index = *self.component_ids.entry(id).or_insert(next);
+        set_name(&mut self.component_names, index, storage.component_name());
+        self.components.insert(id, RwLock::new(storage));
+    }
+
+    /// Takes a resource out of the world, its DepVec bit is kept for when it's inserted again
+    pub fn remove<R: 'static + Any>(&mut self) -> Option<R>{
+        self.resources.remove(&TypeId::of::<R>())
+            .map(|resource| *resource.into_inner().downcast::<R>().unwrap())
+    }
+
+    /// Gets a resource, inserting the result of `f` first if the world doesn't have it
+    pub fn get_or_insert_with<R: 'static + Any, F: FnOnce() -> R>(&mut self, f: F) -> MappedRwLockWriteGuard<'_, R>{
+        if !self.contains::<R>(){
+            self.insert(f());
+        }
+        self.get_mut::<R>()
+    }
+
+    /// Inserts a resource built from the world if it isn't there yet, `Default` types can always be built this way
+    pub fn init_resource<R: 'static + Any + FromWorld>(&mut self){
+        if !self.contains::<R>(){
+            let resource = R::from_world(self);
+            self.insert(resource);
+        }
+    }
+
+    /// Type name of the resource with the given DepVec bit index
+    pub fn resource_name(&self, id: usize) -> Option<&'static str>{
+        self.resource_names.get(id).copied()
+    }
+
+    /// Type name of the component with the given DepVec bit index
+    pub fn component_name(&self, id: usize) -> Option<&'static str>{
+        self.component_names.get(id).copied()
+    }
+}
+
+/// Resources that can build themselves from what's already in the world
+pub trait FromWorld{
+    fn from_world(world: &mut World) -> Self;
+}
+
+impl<T: Default> FromWorld for T{
+    fn from_world(_world: &mut World) -> Self{
+        T::default()
+    }
+}
+
+fn missing<T>() -> !{
+    panic!("Resource {} isn't in the world", std::any::type_name::<T>())
+}
+
+fn set_name(names: &mut Vec<&'static str>, id: usize, name: &'static str){
+    if id >= names.len(){
+        names.resize(id + 1, "");
+    }
+    names[id] = name;
 }
 
 impl WorldCommon for World{
     fn get<T: 'static>(& self) -> MappedRwLockReadGuard<T>{
-        RwLockReadGuard::map(self.resources.get(&TypeId::of::<T>()).unwrap().read(),
+        RwLockReadGuard::map(self.resources.get(&TypeId::of::<T>()).unwrap_or_else(|| missing::<T>()).read(),
             |any| any.downcast_ref::<T>().unwrap())
     }
 
     fn get_mut<T: 'static>(&self) -> MappedRwLockWriteGuard<T>{
-        RwLockWriteGuard::map(self.resources.get(&TypeId::of::<T>()).unwrap().write(),
+        RwLockWriteGuard::map(self.resources.get(&TypeId::of::<T>()).unwrap_or_else(|| missing::<T>()).write(),
             |any| any.downcast_mut::<T>().unwrap())
     }
 
     fn insert<R: 'static + Any>(&mut self, resource: R){
         let id = TypeId::of::<R>();
-        self.resource_ids.insert(id.clone(), self.resource_ids.len());
+        let next = self.resource_ids.len();
+        let index = *self.resource_ids.entry(id).or_insert(next);
+        set_name(&mut self.resource_names, index, std::any::type_name::<R>());
+        self.resource_layouts.insert(id, Layout::new::<R>());
         self.resources.insert(id, RwLock::new(Box::new(resource)));
     }
 
+    fn contains<R: Any>(&self) -> bool{
+        self.resources.contains_key(&TypeId::of::<R>())
+    }
+
     fn get_comp<T: Component + 'static>(&self) -> MappedRwLockReadGuard<ComponentStorage<T>>{
         RwLockReadGuard::map(self.components.get(&TypeId::of::<T>()).unwrap().read(),
-            |any| any.downcast_ref::<VecStorage<T>>().unwrap() as &ComponentStorage<T>)
+            |any| any.as_any().downcast_ref::<VecStorage<T>>().unwrap() as &ComponentStorage<T>)
     }
 
     fn get_comp_mut<T: Component + 'static>(&self) -> MappedRwLockWriteGuard<ComponentStorage<T>>{
         RwLockWriteGuard::map(self.components.get(&TypeId::of::<T>()).unwrap().write(),
-            |any| any.downcast_mut::<VecStorage<T>>().unwrap() as &mut ComponentStorage<T>)
+            |any| any.as_any_mut().downcast_mut::<VecStorage<T>>().unwrap() as &mut ComponentStorage<T>)
     }
 
     fn register_comp<T: Component + 'static>(&mut self){
-        let id = TypeId::of::<T>();
-        self.component_ids.insert(id.clone(), self.component_ids.len());
-        self.components.insert(id, RwLock::new(Box::new(VecStorage::<T>::new())));
+        self.register_storage(TypeId::of::<T>(), Box::new(VecStorage::<T>::new()));
     }
     
+    fn contains_comp<T: Any>(&self) -> bool{
+        self.components.contains_key(&TypeId::of::<T>())
+    }
+
     fn get_dep_vec_res<T: Any>(&self, at: AccessType) -> DepVec{
         let mut res = BitVec::from_elem(self.resource_ids.len(), false);
         res.set(*self.resource_ids.get(&TypeId::of::<T>()).unwrap(), true);
@@ -130,4 +226,48 @@ mod tests{
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn reinsert_keeps_id(){
+        let mut world = World::new();
+        world.insert(1_u32);
+        world.insert(String::from("a"));
+        world.insert(2_u32);
+        assert_eq!(*world.get::<u32>(), 2);
+        assert_eq!(world.resource_ids.len(), 2);
+        assert_eq!(world.resource_name(1), Some(std::any::type_name::<String>()));
+
+        let id = world.resource_ids[&TypeId::of::<u32>()];
+        assert_eq!(world.remove::<u32>(), Some(2));
+        assert_eq!(world.remove::<u32>(), None);
+        assert!(!world.contains::<u32>());
+        world.insert(3_u32);
+        assert_eq!(world.resource_ids[&TypeId::of::<u32>()], id);
+    }
+
+    #[derive(Default)]
+    struct Gravity(f32);
+
+    struct Weight(f32);
+
+    impl FromWorld for Weight{
+        fn from_world(world: &mut World) -> Self{
+            world.init_resource::<Gravity>();
+            Weight(world.get::<Gravity>().0 * 2.0)
+        }
+    }
+
+    #[test]
+    fn init_resources(){
+        let mut world = World::new();
+        *world.get_or_insert_with(|| Gravity(-9.8)) = Gravity(-1.0);
+        assert_eq!(world.get_or_insert_with(|| Gravity(-9.8)).0, -1.0);
+
+        world.init_resource::<Weight>();
+        assert_eq!(world.get::<Weight>().0, -2.0);
+
+        world.remove::<Gravity>();
+        world.init_resource::<Gravity>();
+        assert_eq!(world.get::<Gravity>().0, 0.0);
+    }
+}
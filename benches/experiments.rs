@@ -0,0 +1,63 @@
+//! Criterion benches of a single frame of each experiment, over entity and thread counts.
+//!
+//! cargo bench --bench experiments
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use SmolECS::experiments::{bouncing_balls, nbody, transform, SplitMix64};
+use SmolECS::system::{SystemScheduler, Scheduler};
+use SmolECS::transform::TransformPropagation;
+use std::sync::Arc;
+
+const THREADS: [usize; 2] = [1, 4];
+
+fn pool(threads: usize) -> Arc<rayon::ThreadPool>{
+    Arc::new(rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap())
+}
+
+fn bench_bouncing_balls(c: &mut Criterion){
+    let mut group = c.benchmark_group("bouncing_balls");
+    for &balls in [1000, 10000].iter(){
+        for &threads in THREADS.iter(){
+            let world = bouncing_balls::setup(&bouncing_balls::Config{balls, ..Default::default()});
+            let mut scheduler = SystemScheduler::new(pool(threads));
+            bouncing_balls::add_systems(&mut scheduler);
+            group.bench_with_input(BenchmarkId::new(format!("{} threads", threads), balls), &balls, |b, _| b.iter(|| scheduler.run(&world)));
+        }
+    }
+    group.finish();
+}
+
+fn bench_nbody(c: &mut Criterion){
+    let mut group = c.benchmark_group("nbody");
+    group.sample_size(20);
+    for &particles in [500, 2000].iter(){
+        for &threads in THREADS.iter(){
+            let world = nbody::setup(&nbody::Config{particles, ..Default::default()});
+            let mut scheduler = SystemScheduler::new(pool(threads));
+            nbody::add_systems(&mut scheduler);
+            group.bench_with_input(BenchmarkId::new(format!("{} threads", threads), particles), &particles, |b, _| b.iter(|| scheduler.run(&world)));
+        }
+    }
+    group.finish();
+}
+
+fn bench_transform(c: &mut Criterion){
+    let mut group = c.benchmark_group("transform");
+    for &roots in [4, 32].iter(){
+        for &threads in THREADS.iter(){
+            let config = transform::Config{roots, depth: 4, branching: 4, mutations: roots * 4, ..Default::default()};
+            let (world, nodes) = transform::setup(&config);
+            let mut scheduler = SystemScheduler::new(pool(threads));
+            scheduler.add(TransformPropagation, "propagate", vec![]);
+            let mut rng = SplitMix64::new(1);
+            group.bench_with_input(BenchmarkId::new(format!("{} threads", threads), nodes.len()), &roots, |b, _| b.iter(||{
+                transform::mutate(&world, &nodes, &mut rng, config.mutations);
+                scheduler.run(&world);
+            }));
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_bouncing_balls, bench_nbody, bench_transform);
+criterion_main!(benches);
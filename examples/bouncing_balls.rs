@@ -0,0 +1,28 @@
+//! Runs the BouncingBalls experiment headless and prints the final state hash and frame times.
+//!
+//! cargo run --release --example bouncing_balls -- --balls 1000 --frames 600 --seed 1 --threads 4 [--verbose]
+
+use SmolECS::experiments::{arg, flag, mean, percentile};
+use SmolECS::experiments::bouncing_balls::{run, Config};
+
+fn main(){
+    let args: Vec<String> = std::env::args().collect();
+    let defaults = Config::default();
+    let config = Config{
+        balls: arg(&args, "--balls", defaults.balls),
+        frames: arg(&args, "--frames", defaults.frames),
+        seed: arg(&args, "--seed", defaults.seed),
+        threads: arg(&args, "--threads", defaults.threads),
+    };
+
+    let report = run(&config);
+    if flag(&args, "--verbose"){
+        for (frame, time) in report.frame_times.iter().enumerate(){
+            println!("frame {}: {:?}", frame, time);
+        }
+    }
+    println!("balls {} frames {} seed {} threads {}", config.balls, config.frames, config.seed, config.threads);
+    println!("bounces {}", report.bounces);
+    println!("frame mean {:?} p50 {:?} p99 {:?}", mean(&report.frame_times), percentile(&report.frame_times, 50.0), percentile(&report.frame_times, 99.0));
+    println!("hash {:016x}", report.hash);
+}
@@ -0,0 +1,36 @@
+//! Runs the NBody experiment and checks that momentum and energy were conserved.
+//! Exits with an error if energy drifted more than the tolerance.
+//!
+//! cargo run --release --example nbody -- --particles 1000 --steps 200 --seed 1 --threads 4 [--tolerance 0.01] [--verbose]
+
+use SmolECS::experiments::{arg, flag, mean, percentile};
+use SmolECS::experiments::nbody::{run, Config};
+
+fn main(){
+    let args: Vec<String> = std::env::args().collect();
+    let defaults = Config::default();
+    let config = Config{
+        particles: arg(&args, "--particles", defaults.particles),
+        steps: arg(&args, "--steps", defaults.steps),
+        seed: arg(&args, "--seed", defaults.seed),
+        threads: arg(&args, "--threads", defaults.threads),
+    };
+    let tolerance = arg(&args, "--tolerance", 0.01);
+
+    let report = run(&config);
+    if flag(&args, "--verbose"){
+        for (step, time) in report.step_times.iter().enumerate(){
+            println!("step {}: {:?}", step, time);
+        }
+    }
+    println!("particles {} steps {} seed {} threads {}", config.particles, config.steps, config.seed, config.threads);
+    println!("energy {:.9} -> {:.9}, drift {:.3e}", report.initial.energy(), report.last.energy(), report.energy_drift());
+    println!("momentum {:?} -> {:?}, drift {:.3e}", report.initial.momentum, report.last.momentum, report.momentum_drift());
+    println!("step mean {:?} p50 {:?} p99 {:?}", mean(&report.step_times), percentile(&report.step_times, 50.0), percentile(&report.step_times, 99.0));
+    println!("hash {:016x}", report.hash);
+
+    if report.energy_drift() > tolerance{
+        eprintln!("energy drifted more than {}", tolerance);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,31 @@
+//! Runs the SmolTransform experiment and prints the time spent in each phase and the final checksum.
+//!
+//! cargo run --release --example transform -- --roots 8 --depth 5 --branching 4 --frames 100 --mutations 64 --seed 1 --threads 4
+
+use SmolECS::experiments::{arg, mean, percentile};
+use SmolECS::experiments::transform::{run, Config};
+use std::time::Duration;
+
+fn phase(name: &str, times: &[Duration]){
+    println!("{} mean {:?} p50 {:?} p99 {:?}", name, mean(times), percentile(times, 50.0), percentile(times, 99.0));
+}
+
+fn main(){
+    let args: Vec<String> = std::env::args().collect();
+    let defaults = Config::default();
+    let config = Config{
+        roots: arg(&args, "--roots", defaults.roots),
+        depth: arg(&args, "--depth", defaults.depth),
+        branching: arg(&args, "--branching", defaults.branching),
+        frames: arg(&args, "--frames", defaults.frames),
+        mutations: arg(&args, "--mutations", defaults.mutations),
+        seed: arg(&args, "--seed", defaults.seed),
+        threads: arg(&args, "--threads", defaults.threads),
+    };
+
+    let report = run(&config);
+    println!("nodes {} frames {} mutations {} seed {} threads {}", report.nodes, config.frames, config.mutations, config.seed, config.threads);
+    phase("mutate", &report.mutate_times);
+    phase("propagate", &report.propagate_times);
+    println!("checksum {:016x}", report.hash);
+}
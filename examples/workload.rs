@@ -0,0 +1,26 @@
+//! Runs a synthetic workload from a TOML or JSON config, JSON if the file ends in .json.
+//!
+//! cargo run --release --features serde --example workload -- examples/workload.toml
+
+use SmolECS::experiments::{mean, percentile};
+use SmolECS::experiments::workload::{Config, Workload};
+use std::time::Duration;
+
+fn phase(name: &str, times: &[Duration]){
+    println!("{} mean {:?} p50 {:?} p99 {:?}", name, mean(times), percentile(times, 50.0), percentile(times, 99.0));
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>>{
+    let path = std::env::args().nth(1).unwrap_or_else(|| "examples/workload.toml".to_string());
+    let data = std::fs::read_to_string(&path)?;
+    let config = if path.ends_with(".json") { Config::from_json(&data)? } else { Config::from_toml(&data)? };
+
+    let mut workload = Workload::new(config)?;
+    let report = workload.run();
+    println!("{}: {} components, {} systems, {} frames", path, workload.config().components.len(), workload.config().systems.len(), workload.config().frames);
+    println!("live entities {}", report.live);
+    phase("churn", &report.churn_times);
+    phase("systems", &report.system_times);
+    println!("hash {:016x}", report.hash);
+    Ok(())
+}
@@ -0,0 +1,46 @@
+//! Runs every experiment over a matrix of entity counts, thread counts and backends and prints a CSV row per run.
+//!
+//! cargo run --release --bin benchmark -- --experiments bouncing_balls,nbody,transform --backends hbs
+//!     --entities 1000,10000 --threads 1,2,4 --frames 100 --seed 1 [--out results.csv]
+//!
+//! Only one configuration can be measured so far: hbs, the hybrid bitset world, with every component in a
+//! `VecStorage`. The storage column says which storage a row used.
+//!
+//! peak_bytes is the most memory the run had allocated at once on top of what was live before it started.
+
+use SmolECS::experiments::{arg, list};
+use SmolECS::experiments::bench::{matrix, run, Backend, CountingAlloc, Experiment, Row, CSV_HEADER};
+use std::fs::File;
+use std::io::{self, Write};
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc::new();
+
+fn main() -> io::Result<()>{
+    let args: Vec<String> = std::env::args().collect();
+    let runs = matrix(
+        &list(&args, "--experiments", Experiment::ALL.to_vec()),
+        &list(&args, "--backends", Backend::ALL.to_vec()),
+        &list(&args, "--entities", vec![1000, 10000]),
+        &list(&args, "--threads", vec![1, 2, 4]),
+        arg(&args, "--frames", 100),
+        arg(&args, "--seed", 1),
+    );
+
+    let mut out: Box<dyn Write> = match args.iter().position(|arg| arg == "--out").and_then(|i| args.get(i + 1)){
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+
+    writeln!(out, "{}", CSV_HEADER)?;
+    for (n, cell) in runs.iter().enumerate(){
+        eprintln!("[{}/{}] {} {} entities {} threads {}", n + 1, runs.len(), cell.experiment.name(), cell.backend.name(), cell.entities, cell.threads);
+        let baseline = ALLOC.live();
+        ALLOC.reset_peak();
+        let (frame_times, hash) = run(cell);
+        let peak_bytes = ALLOC.peak().saturating_sub(baseline);
+        writeln!(out, "{}", Row::new(*cell, &frame_times, peak_bytes, hash).to_csv())?;
+        out.flush()?;
+    }
+    Ok(())
+}
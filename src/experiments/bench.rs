@@ -0,0 +1,271 @@
+//! Runs the experiments over a matrix of entity counts, thread counts and backends and formats the results as CSV.
+//! Used by the `benchmark` binary, which also counts allocations so memory use can go in the table.
+
+use super::{bouncing_balls, nbody, transform, mean, percentile};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Experiment{
+    BouncingBalls,
+    NBody,
+    Transform,
+}
+
+impl Experiment{
+    pub const ALL: [Experiment; 3] = [Experiment::BouncingBalls, Experiment::NBody, Experiment::Transform];
+
+    pub fn name(&self) -> &'static str{
+        match self{
+            Experiment::BouncingBalls => "bouncing_balls",
+            Experiment::NBody => "nbody",
+            Experiment::Transform => "transform",
+        }
+    }
+}
+
+impl FromStr for Experiment{
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err>{
+        Experiment::ALL.iter().copied()
+            .find(|experiment| experiment.name() == name)
+            .ok_or_else(|| format!("Unknown experiment {}", name))
+    }
+}
+
+/// The world implementation the experiment runs on.
+/// SmolARCECS doesn't have a world yet and the hybrid bitset world always uses `VecStorage`, so there is only
+/// one configuration to measure for now. The CSV has its storage in its own column so that stays visible.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend{
+    Hbs,
+}
+
+impl Backend{
+    pub const ALL: [Backend; 1] = [Backend::Hbs];
+
+    pub fn name(&self) -> &'static str{
+        match self{
+            Backend::Hbs => "hbs",
+        }
+    }
+
+    /// The component storage the backend's world keeps components in
+    pub fn storage(&self) -> &'static str{
+        match self{
+            Backend::Hbs => "vec",
+        }
+    }
+}
+
+impl FromStr for Backend{
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err>{
+        Backend::ALL.iter().copied()
+            .find(|backend| backend.name() == name)
+            .ok_or_else(|| format!("Unknown backend {}", name))
+    }
+}
+
+/// One cell of the matrix
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Run{
+    pub experiment: Experiment,
+    pub backend: Backend,
+    pub entities: usize,
+    pub threads: usize,
+    pub frames: usize,
+    pub seed: u64,
+}
+
+/// Every combination of the lists, experiments outermost
+pub fn matrix(experiments: &[Experiment], backends: &[Backend], entities: &[usize], threads: &[usize], frames: usize, seed: u64) -> Vec<Run>{
+    let mut runs = Vec::new();
+    for &experiment in experiments{
+        for &backend in backends{
+            for &entities in entities{
+                for &threads in threads{
+                    runs.push(Run{experiment, backend, entities, threads, frames, seed});
+                }
+            }
+        }
+    }
+    runs
+}
+
+// Trees of 4 levels with 4 children each, as many as it takes to get close to the entity count
+const TREE_DEPTH: usize = 3;
+const TREE_BRANCHING: usize = 4;
+const TREE_SIZE: usize = 1 + 4 + 16 + 64;
+
+/// Runs one cell, returning the frame times and the final state hash
+pub fn run(run: &Run) -> (Vec<Duration>, u64){
+    match (run.backend, run.experiment){
+        (Backend::Hbs, Experiment::BouncingBalls) => {
+            let report = bouncing_balls::run(&bouncing_balls::Config{
+                balls: run.entities,
+                frames: run.frames,
+                seed: run.seed,
+                threads: run.threads,
+            });
+            (report.frame_times, report.hash)
+        },
+        (Backend::Hbs, Experiment::NBody) => {
+            let report = nbody::run(&nbody::Config{
+                particles: run.entities,
+                steps: run.frames,
+                seed: run.seed,
+                threads: run.threads,
+            });
+            (report.step_times, report.hash)
+        },
+        (Backend::Hbs, Experiment::Transform) => {
+            let roots = (run.entities / TREE_SIZE).max(1);
+            let report = transform::run(&transform::Config{
+                roots,
+                depth: TREE_DEPTH,
+                branching: TREE_BRANCHING,
+                frames: run.frames,
+                mutations: (roots * TREE_SIZE / 100).max(1),
+                seed: run.seed,
+                threads: run.threads,
+            });
+            let frame_times = report.mutate_times.iter().zip(report.propagate_times.iter()).map(|(a, b)| *a + *b).collect();
+            (frame_times, report.hash)
+        },
+    }
+}
+
+pub const CSV_HEADER: &str = "experiment,backend,storage,entities,threads,frames,mean_us,p50_us,p99_us,peak_bytes,hash";
+
+/// A finished run, one line of the CSV
+#[derive(Clone, Debug)]
+pub struct Row{
+    pub run: Run,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p99: Duration,
+    /// Most bytes allocated at once during the run, 0 if allocations weren't counted
+    pub peak_bytes: usize,
+    pub hash: u64,
+}
+
+impl Row{
+    pub fn new(run: Run, frame_times: &[Duration], peak_bytes: usize, hash: u64) -> Self{
+        Row{
+            run,
+            mean: mean(frame_times),
+            p50: percentile(frame_times, 50.0),
+            p99: percentile(frame_times, 99.0),
+            peak_bytes,
+            hash,
+        }
+    }
+
+    pub fn to_csv(&self) -> String{
+        format!("{},{},{},{},{},{},{:.3},{:.3},{:.3},{},{:016x}",
+            self.run.experiment.name(),
+            self.run.backend.name(),
+            self.run.backend.storage(),
+            self.run.entities,
+            self.run.threads,
+            self.run.frames,
+            self.mean.as_secs_f64() * 1e6,
+            self.p50.as_secs_f64() * 1e6,
+            self.p99.as_secs_f64() * 1e6,
+            self.peak_bytes,
+            self.hash,
+        )
+    }
+}
+
+/// Allocator that keeps track of how many bytes are live and the most there have been since the last reset.
+/// Install it with `#[global_allocator]` to get memory use into the rows.
+pub struct CountingAlloc{
+    live: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl CountingAlloc{
+    pub const fn new() -> Self{
+        CountingAlloc{
+            live: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn live(&self) -> usize{
+        self.live.load(Ordering::Relaxed)
+    }
+
+    pub fn peak(&self) -> usize{
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    /// Starts counting the peak again from what's live now
+    pub fn reset_peak(&self){
+        self.peak.store(self.live(), Ordering::Relaxed);
+    }
+
+    fn added(&self, size: usize){
+        let live = self.live.fetch_add(size, Ordering::Relaxed) + size;
+        self.peak.fetch_max(live, Ordering::Relaxed);
+    }
+}
+
+impl Default for CountingAlloc{
+    fn default() -> Self{
+        CountingAlloc::new()
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAlloc{
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8{
+        let ptr = System.alloc(layout);
+        if !ptr.is_null(){
+            self.added(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout){
+        System.dealloc(ptr, layout);
+        self.live.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8{
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null(){
+            self.live.fetch_sub(layout.size(), Ordering::Relaxed);
+            self.added(new_size);
+        }
+        new
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn matrix_rows(){
+        let runs = matrix(&Experiment::ALL, &Backend::ALL, &[50, 100], &[1, 2], 3, 1);
+        assert_eq!(runs.len(), 3 * 2 * 2);
+        assert_eq!(runs[0].experiment, Experiment::BouncingBalls);
+        assert_eq!(runs[11].experiment, Experiment::Transform);
+        assert_eq!("nbody".parse(), Ok(Experiment::NBody));
+        assert!("archetype".parse::<Backend>().is_err());
+
+        for cell in runs.iter().filter(|cell| cell.threads == 1){
+            let (times, hash) = run(cell);
+            assert_eq!(times.len(), 3);
+            let row = Row::new(*cell, &times, 1024, hash).to_csv();
+            assert_eq!(row.split(',').count(), CSV_HEADER.split(',').count());
+            assert!(row.starts_with(&format!("{},hbs,vec,{},1,3,", cell.experiment.name(), cell.entities)));
+        }
+    }
+}
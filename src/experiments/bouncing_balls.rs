@@ -0,0 +1,316 @@
+//! BouncingBalls: balls moving around a box, bouncing off the walls and each other.
+//! Every wall bounce counts up on the ball and changes its color.
+
+use super::{SplitMix64, StateHash};
+use crate::world::{World, WorldCommon};
+use crate::entity::EntityStorage;
+use crate::system::{System, ReadComp, WriteComp, Read, Joinable, SystemScheduler, Scheduler};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position{
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Velocity{
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Radius(pub f32);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounces(pub u32);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color(pub u32);
+
+/// Size of the box and the time step
+#[derive(Clone, Copy, Debug)]
+pub struct Arena{
+    pub width: f32,
+    pub height: f32,
+    pub dt: f32,
+}
+
+impl Default for Arena{
+    fn default() -> Self{
+        Arena{
+            width: 100.0,
+            height: 100.0,
+            dt: 1.0 / 60.0,
+        }
+    }
+}
+
+pub const MAX_RADIUS: f32 = 4.0;
+
+pub struct Movement;
+
+impl<'d, 'w: 'd> System<'d, 'w, World> for Movement{
+    type SystemData = (WriteComp<'d, Position>, ReadComp<'d, Velocity>, Read<'d, Arena>);
+
+    fn run(&self, (mut positions, velocities, arena): Self::SystemData){
+        for (position, velocity) in (&mut positions, &velocities).join(){
+            position.x += velocity.x * arena.dt;
+            position.y += velocity.y * arena.dt;
+        }
+    }
+}
+
+pub struct Walls;
+
+// Keeps the ball in the box, true if it hit a wall
+fn bounce(position: &mut f32, velocity: &mut f32, radius: f32, size: f32) -> bool{
+    if *position - radius < 0.0{
+        *position = radius;
+        *velocity = velocity.abs();
+        true
+    }
+    else if *position + radius > size{
+        *position = size - radius;
+        *velocity = -velocity.abs();
+        true
+    }
+    else{
+        false
+    }
+}
+
+impl<'d, 'w: 'd> System<'d, 'w, World> for Walls{
+    type SystemData = (
+        WriteComp<'d, Position>,
+        WriteComp<'d, Velocity>,
+        ReadComp<'d, Radius>,
+        WriteComp<'d, Bounces>,
+        WriteComp<'d, Color>,
+        Read<'d, Arena>,
+    );
+
+    fn run(&self, (mut positions, mut velocities, radii, mut bounces, mut colors, arena): Self::SystemData){
+        for (position, velocity, radius, bounces, color) in (&mut positions, &mut velocities, &radii, &mut bounces, &mut colors).join(){
+            let hit_x = bounce(&mut position.x, &mut velocity.x, radius.0, arena.width);
+            let hit_y = bounce(&mut position.y, &mut velocity.y, radius.0, arena.height);
+            if hit_x || hit_y{
+                bounces.0 += 1;
+                color.0 = color.0.rotate_left(8) ^ bounces.0;
+            }
+        }
+    }
+}
+
+/// Equal mass elastic collisions, found through a uniform grid
+pub struct Collisions;
+
+impl<'d, 'w: 'd> System<'d, 'w, World> for Collisions{
+    type SystemData = (
+        Read<'d, EntityStorage>,
+        ReadComp<'d, Position>,
+        ReadComp<'d, Radius>,
+        WriteComp<'d, Velocity>,
+    );
+
+    fn run(&self, (entities, positions, radii, mut velocities): Self::SystemData){
+        let cell = 2.0 * MAX_RADIUS;
+        let balls: Vec<(usize, Position, f32)> = (&*entities, &positions, &radii).join()
+            .map(|(entity, position, radius)| (entity.index(), *position, radius.0))
+            .collect();
+
+        let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        for (n, (_, position, _)) in balls.iter().enumerate(){
+            grid.entry(((position.x / cell) as i32, (position.y / cell) as i32)).or_default().push(n);
+        }
+
+        for (n, (index, position, radius)) in balls.iter().enumerate(){
+            let (cx, cy) = ((position.x / cell) as i32, (position.y / cell) as i32);
+            for dx in -1..=1{
+                for dy in -1..=1{
+                    let others = match grid.get(&(cx + dx, cy + dy)){
+                        Some(others) => others,
+                        None => continue,
+                    };
+                    // Cells list balls in order, so each pair is only handled from its lower ball
+                    for &m in others.iter().filter(|&&m| m > n){
+                        let (other, other_position, other_radius) = balls[m];
+                        let (nx, ny) = (other_position.x - position.x, other_position.y - position.y);
+                        let distance = (nx * nx + ny * ny).sqrt();
+                        if distance == 0.0 || distance > radius + other_radius{
+                            continue;
+                        }
+                        let (nx, ny) = (nx / distance, ny / distance);
+
+                        let a = *velocities.get(index).unwrap();
+                        let b = *velocities.get(&other).unwrap();
+                        let approach = (b.x - a.x) * nx + (b.y - a.y) * ny;
+                        if approach >= 0.0{
+                            continue;
+                        }
+                        velocities.set(*index, Velocity{x: a.x + approach * nx, y: a.y + approach * ny});
+                        velocities.set(other, Velocity{x: b.x - approach * nx, y: b.y - approach * ny});
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Config{
+    pub balls: usize,
+    pub frames: usize,
+    pub seed: u64,
+    pub threads: usize,
+}
+
+impl Default for Config{
+    fn default() -> Self{
+        Config{
+            balls: 1000,
+            frames: 600,
+            seed: 1,
+            threads: 4,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Report{
+    /// Hash of every ball's final state, the same config always gives the same hash
+    pub hash: u64,
+    pub frame_times: Vec<Duration>,
+    pub bounces: u64,
+}
+
+/// Builds the world with the balls spread around a box sized so density stays the same for any ball count
+pub fn setup(config: &Config) -> World{
+    let mut world = World::new();
+    let side = (config.balls as f32).sqrt() * 4.0 * MAX_RADIUS;
+    world.insert(Arena{
+        width: side,
+        height: side,
+        ..Arena::default()
+    });
+    world.insert(EntityStorage::new());
+    world.register_comp::<Position>();
+    world.register_comp::<Velocity>();
+    world.register_comp::<Radius>();
+    world.register_comp::<Bounces>();
+    world.register_comp::<Color>();
+
+    let mut rng = SplitMix64::new(config.seed);
+    for _ in 0..config.balls{
+        let entity = *world.get_mut::<EntityStorage>().create_entity();
+        let radius = rng.range(MAX_RADIUS / 2.0, MAX_RADIUS);
+        let index = entity.index();
+        world.get_comp_mut::<Position>().set(&index, Position{x: rng.range(radius, side - radius), y: rng.range(radius, side - radius)});
+        world.get_comp_mut::<Velocity>().set(&index, Velocity{x: rng.range(-50.0, 50.0), y: rng.range(-50.0, 50.0)});
+        world.get_comp_mut::<Radius>().set(&index, Radius(radius));
+        world.get_comp_mut::<Bounces>().set(&index, Bounces(0));
+        world.get_comp_mut::<Color>().set(&index, Color(rng.next_u64() as u32));
+    }
+    world
+}
+
+/// Movement, then walls, then collisions
+pub fn add_systems<'d, 'w: 'd>(scheduler: &mut SystemScheduler<'d, 'w>){
+    scheduler.add(Movement, "movement", vec![]);
+    scheduler.add(Walls, "walls", vec!["movement"]);
+    scheduler.add(Collisions, "collisions", vec!["walls"]);
+}
+
+pub fn hash(world: &World) -> u64{
+    let mut hash = StateHash::default();
+    let entities = world.get::<EntityStorage>();
+    let positions = world.get_comp::<Position>();
+    let velocities = world.get_comp::<Velocity>();
+    let bounces = world.get_comp::<Bounces>();
+    let colors = world.get_comp::<Color>();
+    for entity in entities.live_entities(){
+        let index = entity.index();
+        let (position, velocity) = (positions.get(&index).unwrap(), velocities.get(&index).unwrap());
+        hash.write_u64(index as u64);
+        hash.write_f32(position.x);
+        hash.write_f32(position.y);
+        hash.write_f32(velocity.x);
+        hash.write_f32(velocity.y);
+        hash.write_u64(u64::from(bounces.get(&index).unwrap().0));
+        hash.write_u64(u64::from(colors.get(&index).unwrap().0));
+    }
+    hash.finish()
+}
+
+pub fn run(config: &Config) -> Report{
+    let world = setup(config);
+    let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(config.threads.max(1)).build().unwrap());
+    let mut scheduler = SystemScheduler::new(pool);
+    add_systems(&mut scheduler);
+
+    let frame_times = (0..config.frames).map(|_|{
+        let start = Instant::now();
+        scheduler.run(&world);
+        start.elapsed()
+    }).collect();
+
+    let bounces = world.get_comp::<Bounces>().iter()
+        .filter_map(|(valid, bounces)| if valid { bounces.map(|b| u64::from(b.0)) } else { None })
+        .sum();
+
+    Report{
+        hash: hash(&world),
+        frame_times,
+        bounces,
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn config(threads: usize) -> Config{
+        Config{
+            balls: 200,
+            frames: 120,
+            seed: 7,
+            threads,
+        }
+    }
+
+    #[test]
+    fn deterministic(){
+        let report = run(&config(1));
+        assert_eq!(report.frame_times.len(), 120);
+        assert!(report.bounces > 0);
+        // Recorded from a known good run, a world change that keeps the simulation the same has to land on it too
+        assert_eq!(report.hash, 0xf5e4_a969_1329_a7b5);
+        assert_eq!(report.hash, run(&config(1)).hash);
+        assert_eq!(report.hash, run(&config(4)).hash);
+        assert_ne!(report.hash, run(&Config{seed: 8, ..config(1)}).hash);
+    }
+
+    #[test]
+    fn balls_stay_in_the_box(){
+        let world = setup(&config(1));
+        {
+            let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+            let mut scheduler = SystemScheduler::new(pool);
+            add_systems(&mut scheduler);
+            for _ in 0..60{
+                scheduler.run(&world);
+            }
+        }
+
+        let arena = *world.get::<Arena>();
+        let positions = world.get_comp::<Position>();
+        let radii = world.get_comp::<Radius>();
+        for entity in world.get::<EntityStorage>().live_entities(){
+            let (position, radius) = (positions.get(&entity.index()).unwrap(), radii.get(&entity.index()).unwrap().0);
+            assert!(position.x >= radius && position.x <= arena.width - radius);
+            assert!(position.y >= radius && position.y <= arena.height - radius);
+        }
+    }
+}
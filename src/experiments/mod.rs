@@ -0,0 +1,137 @@
+//! The experiments from the README, built only on the public facade. They run on the HBS world it re-exports,
+//! the only backend there is so far.
+
+pub mod bench;
+pub mod bouncing_balls;
+pub mod nbody;
+pub mod transform;
+#[cfg(feature = "serde")]
+pub mod workload;
+
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Small seeded generator so experiment runs are the same everywhere
+#[derive(Clone, Debug)]
+pub struct SplitMix64{
+    state: u64,
+}
+
+impl SplitMix64{
+    pub fn new(seed: u64) -> Self{
+        SplitMix64{
+            state: seed,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64{
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32{
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn range(&mut self, min: f32, max: f32) -> f32{
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Uniform in [0, n)
+    pub fn below(&mut self, n: usize) -> usize{
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// FNV-1a, used for the experiments' final state hashes
+#[derive(Clone, Debug)]
+pub struct StateHash(u64);
+
+impl Default for StateHash{
+    fn default() -> Self{
+        StateHash(0xCBF2_9CE4_8422_2325)
+    }
+}
+
+impl StateHash{
+    pub fn write_u64(&mut self, value: u64){
+        for byte in value.to_le_bytes().iter(){
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01B3);
+        }
+    }
+
+    pub fn write_f32(&mut self, value: f32){
+        self.write_u64(u64::from(value.to_bits()));
+    }
+
+    pub fn finish(&self) -> u64{
+        self.0
+    }
+}
+
+pub fn mean(times: &[Duration]) -> Duration{
+    if times.is_empty(){
+        return Duration::default();
+    }
+    times.iter().sum::<Duration>() / times.len() as u32
+}
+
+/// The time `p` percent of frames were at or under, nearest rank
+pub fn percentile(times: &[Duration], p: f64) -> Duration{
+    if times.is_empty(){
+        return Duration::default();
+    }
+    let mut sorted = times.to_vec();
+    sorted.sort();
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Value of `--name value` in the arguments, or the default if it's missing or doesn't parse
+pub fn arg<T: FromStr>(args: &[String], name: &str, default: T) -> T{
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Comma separated values of `--name a,b,c`, or the defaults if it's missing or any value doesn't parse
+pub fn list<T: FromStr>(args: &[String], name: &str, default: Vec<T>) -> Vec<T>{
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+        .and_then(|values| values.split(',').map(|value| value.trim().parse().ok()).collect())
+        .unwrap_or(default)
+}
+
+pub fn flag(args: &[String], name: &str) -> bool{
+    args.iter().any(|arg| arg == name)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn percentiles(){
+        let times: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+        assert_eq!(percentile(&times, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&times, 99.0), Duration::from_millis(99));
+        assert_eq!(mean(&times), Duration::from_micros(50_500));
+
+        let args: Vec<String> = ["--balls", "12", "--verbose"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(arg(&args, "--balls", 0), 12);
+        assert_eq!(arg(&args, "--frames", 7), 7);
+        assert!(flag(&args, "--verbose"));
+
+        let args: Vec<String> = ["--threads", "1,2, 4"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(list(&args, "--threads", vec![8]), vec![1, 2, 4]);
+        assert_eq!(list(&args, "--entities", vec![8]), vec![8]);
+    }
+}
@@ -0,0 +1,337 @@
+//! NBody: particles pulling on each other with gravity, every pair summed directly.
+//! Gravity between a pair is equal and opposite and the integrator is symplectic, so total momentum
+//! should stay where it started and total energy should only wobble. How far they move is the correctness check.
+
+use super::{SplitMix64, StateHash};
+use crate::world::{World, WorldCommon};
+use crate::entity::EntityStorage;
+use crate::system::{System, ReadComp, WriteComp, Read, Joinable, SystemScheduler, Scheduler};
+use rayon::prelude::*;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Position{
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Velocity{
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mass(pub f64);
+
+/// Sum of the gravity on a particle, filled in by `Forces` every step
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Acceleration{
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+/// Gravitational constant, softening length and time step
+#[derive(Clone, Copy, Debug)]
+pub struct Gravity{
+    pub g: f64,
+    pub softening: f64,
+    pub dt: f64,
+}
+
+impl Default for Gravity{
+    fn default() -> Self{
+        Gravity{
+            g: 1.0,
+            softening: 0.05,
+            dt: 0.001,
+        }
+    }
+}
+
+// Position, mass and index of every particle, in entity order
+fn bodies(entities: &EntityStorage, positions: &ReadComp<Position>, masses: &ReadComp<Mass>) -> Vec<(usize, Position, f64)>{
+    (entities, positions, masses).join()
+        .map(|(entity, position, mass)| (entity.index(), *position, mass.0))
+        .collect()
+}
+
+/// Accumulates the pull of every other particle, one particle per task on the scheduler's pool
+pub struct Forces;
+
+impl<'d, 'w: 'd> System<'d, 'w, World> for Forces{
+    type SystemData = (
+        Read<'d, EntityStorage>,
+        ReadComp<'d, Position>,
+        ReadComp<'d, Mass>,
+        WriteComp<'d, Acceleration>,
+        Read<'d, Gravity>,
+    );
+
+    fn run(&self, (entities, positions, masses, mut accelerations, gravity): Self::SystemData){
+        let bodies = bodies(&entities, &positions, &masses);
+        let epsilon = gravity.softening * gravity.softening;
+
+        // Each particle sums the others in the same order whatever the thread count, so results don't depend on it
+        let sums: Vec<Acceleration> = bodies.par_iter()
+            .map(|(_, position, _)|{
+                let mut sum = Acceleration::default();
+                for (_, other, mass) in bodies.iter(){
+                    let (dx, dy, dz) = (other.x - position.x, other.y - position.y, other.z - position.z);
+                    let distance = dx * dx + dy * dy + dz * dz + epsilon;
+                    let pull = gravity.g * mass / (distance * distance.sqrt());
+                    sum.x += pull * dx;
+                    sum.y += pull * dy;
+                    sum.z += pull * dz;
+                }
+                sum
+            })
+            .collect();
+
+        for ((index, _, _), sum) in bodies.iter().zip(sums){
+            accelerations.set(*index, sum);
+        }
+    }
+}
+
+/// Semi-implicit Euler, velocity first and then position with the new velocity
+pub struct Integrate;
+
+impl<'d, 'w: 'd> System<'d, 'w, World> for Integrate{
+    type SystemData = (
+        WriteComp<'d, Position>,
+        WriteComp<'d, Velocity>,
+        ReadComp<'d, Acceleration>,
+        Read<'d, Gravity>,
+    );
+
+    fn run(&self, (mut positions, mut velocities, accelerations, gravity): Self::SystemData){
+        let dt = gravity.dt;
+        for (position, velocity, acceleration) in (&mut positions, &mut velocities, &accelerations).join(){
+            velocity.x += acceleration.x * dt;
+            velocity.y += acceleration.y * dt;
+            velocity.z += acceleration.z * dt;
+            position.x += velocity.x * dt;
+            position.y += velocity.y * dt;
+            position.z += velocity.z * dt;
+        }
+    }
+}
+
+/// Conserved quantities of the whole system
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Totals{
+    pub momentum: [f64; 3],
+    pub kinetic: f64,
+    pub potential: f64,
+}
+
+impl Totals{
+    pub fn energy(&self) -> f64{
+        self.kinetic + self.potential
+    }
+}
+
+fn potential(bodies: &[(usize, Position, f64)], gravity: &Gravity) -> f64{
+    let epsilon = gravity.softening * gravity.softening;
+    bodies.par_iter().enumerate()
+        .map(|(n, (_, position, mass))|{
+            bodies[n + 1..].iter().map(|(_, other, other_mass)|{
+                let (dx, dy, dz) = (other.x - position.x, other.y - position.y, other.z - position.z);
+                -gravity.g * mass * other_mass / (dx * dx + dy * dy + dz * dz + epsilon).sqrt()
+            }).sum::<f64>()
+        })
+        .collect::<Vec<f64>>()
+        .iter()
+        .sum()
+}
+
+pub fn totals(world: &World) -> Totals{
+    let gravity = *world.get::<Gravity>();
+    let entities = world.get::<EntityStorage>();
+    let positions = world.get_comp::<Position>();
+    let velocities = world.get_comp::<Velocity>();
+    let masses = world.get_comp::<Mass>();
+
+    let mut totals = Totals::default();
+    let mut bodies = Vec::new();
+    for entity in entities.live_entities(){
+        let index = entity.index();
+        let (position, velocity, mass) = (*positions.get(&index).unwrap(), velocities.get(&index).unwrap(), masses.get(&index).unwrap().0);
+        totals.momentum[0] += mass * velocity.x;
+        totals.momentum[1] += mass * velocity.y;
+        totals.momentum[2] += mass * velocity.z;
+        totals.kinetic += 0.5 * mass * (velocity.x * velocity.x + velocity.y * velocity.y + velocity.z * velocity.z);
+        bodies.push((index, position, mass));
+    }
+    totals.potential = potential(&bodies, &gravity);
+    totals
+}
+
+#[derive(Clone, Debug)]
+pub struct Config{
+    pub particles: usize,
+    pub steps: usize,
+    pub seed: u64,
+    pub threads: usize,
+}
+
+impl Default for Config{
+    fn default() -> Self{
+        Config{
+            particles: 1000,
+            steps: 200,
+            seed: 1,
+            threads: 4,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Report{
+    pub hash: u64,
+    pub step_times: Vec<Duration>,
+    pub initial: Totals,
+    pub last: Totals,
+}
+
+impl Report{
+    /// How far total momentum moved, relative to the momentum the particles carry
+    pub fn momentum_drift(&self) -> f64{
+        let change: f64 = (0..3).map(|n| (self.last.momentum[n] - self.initial.momentum[n]).powi(2)).sum();
+        change.sqrt() / (2.0 * self.initial.kinetic).sqrt().max(f64::MIN_POSITIVE)
+    }
+
+    /// How far total energy moved, relative to where it started
+    pub fn energy_drift(&self) -> f64{
+        ((self.last.energy() - self.initial.energy()) / self.initial.energy()).abs()
+    }
+}
+
+/// Builds a ball of particles with total mass 1, at rest as a whole and with velocities scaled so the ball neither
+/// flies apart nor collapses right away
+pub fn setup(config: &Config) -> World{
+    let mut world = World::new();
+    world.insert(Gravity::default());
+    world.insert(EntityStorage::new());
+    world.register_comp::<Position>();
+    world.register_comp::<Velocity>();
+    world.register_comp::<Mass>();
+    world.register_comp::<Acceleration>();
+
+    let mut rng = SplitMix64::new(config.seed);
+    let mass = 1.0 / config.particles.max(1) as f64;
+    let mut bodies = Vec::with_capacity(config.particles);
+    for _ in 0..config.particles{
+        // Rejection sampling inside the unit sphere
+        let position = loop{
+            let (x, y, z) = (rng.range(-1.0, 1.0) as f64, rng.range(-1.0, 1.0) as f64, rng.range(-1.0, 1.0) as f64);
+            if x * x + y * y + z * z <= 1.0{
+                break Position{x, y, z};
+            }
+        };
+        let velocity = Velocity{x: rng.range(-1.0, 1.0) as f64, y: rng.range(-1.0, 1.0) as f64, z: rng.range(-1.0, 1.0) as f64};
+        bodies.push((position, velocity));
+    }
+
+    // Take out the drift of the whole ball, then scale into virial equilibrium, 2K = -W
+    let count = bodies.len().max(1) as f64;
+    let drift = bodies.iter().fold([0.0; 3], |sum, (_, v)| [sum[0] + v.x / count, sum[1] + v.y / count, sum[2] + v.z / count]);
+    let kinetic: f64 = bodies.iter().map(|(_, v)|{
+        let (x, y, z) = (v.x - drift[0], v.y - drift[1], v.z - drift[2]);
+        0.5 * mass * (x * x + y * y + z * z)
+    }).sum();
+    let indexed: Vec<(usize, Position, f64)> = bodies.iter().enumerate().map(|(n, (p, _))| (n, *p, mass)).collect();
+    let scale = if kinetic > 0.0 { (-potential(&indexed, &Gravity::default()) / (2.0 * kinetic)).sqrt() } else { 0.0 };
+
+    for (position, velocity) in bodies{
+        let index = world.get_mut::<EntityStorage>().create_entity().index();
+        world.get_comp_mut::<Position>().set(&index, position);
+        world.get_comp_mut::<Velocity>().set(&index, Velocity{
+            x: (velocity.x - drift[0]) * scale,
+            y: (velocity.y - drift[1]) * scale,
+            z: (velocity.z - drift[2]) * scale,
+        });
+        world.get_comp_mut::<Mass>().set(&index, Mass(mass));
+        world.get_comp_mut::<Acceleration>().set(&index, Acceleration::default());
+    }
+    world
+}
+
+/// Forces, then integration
+pub fn add_systems<'d, 'w: 'd>(scheduler: &mut SystemScheduler<'d, 'w>){
+    scheduler.add(Forces, "forces", vec![]);
+    scheduler.add(Integrate, "integrate", vec!["forces"]);
+}
+
+pub fn hash(world: &World) -> u64{
+    let mut hash = StateHash::default();
+    let positions = world.get_comp::<Position>();
+    let velocities = world.get_comp::<Velocity>();
+    for entity in world.get::<EntityStorage>().live_entities(){
+        let index = entity.index();
+        let (position, velocity) = (positions.get(&index).unwrap(), velocities.get(&index).unwrap());
+        hash.write_u64(index as u64);
+        for value in [position.x, position.y, position.z, velocity.x, velocity.y, velocity.z].iter(){
+            hash.write_u64(value.to_bits());
+        }
+    }
+    hash.finish()
+}
+
+pub fn run(config: &Config) -> Report{
+    let world = setup(config);
+    let initial = totals(&world);
+    let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(config.threads.max(1)).build().unwrap());
+    let mut scheduler = SystemScheduler::new(pool);
+    add_systems(&mut scheduler);
+
+    let step_times = (0..config.steps).map(|_|{
+        let start = Instant::now();
+        scheduler.run(&world);
+        start.elapsed()
+    }).collect();
+
+    Report{
+        hash: hash(&world),
+        step_times,
+        initial,
+        last: totals(&world),
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn config(threads: usize) -> Config{
+        Config{
+            particles: 64,
+            steps: 200,
+            seed: 3,
+            threads,
+        }
+    }
+
+    #[test]
+    fn conserves(){
+        let report = run(&config(2));
+        assert_eq!(report.step_times.len(), 200);
+        assert!(report.initial.momentum.iter().all(|p| p.abs() < 1e-12));
+        assert!(report.momentum_drift() < 1e-9, "momentum drift {}", report.momentum_drift());
+        assert!(report.energy_drift() < 1e-2, "energy drift {}", report.energy_drift());
+        // The particles did actually move
+        assert!(report.last != report.initial);
+    }
+
+    #[test]
+    fn deterministic(){
+        let hash = run(&config(1)).hash;
+        assert_eq!(hash, run(&config(4)).hash);
+        assert_ne!(hash, run(&Config{seed: 4, ..config(1)}).hash);
+    }
+}
@@ -0,0 +1,229 @@
+//! SmolTransform: a forest of transforms that gets reshaped every frame.
+//! Each frame some random nodes get a new scale and some get moved under a random new parent, then every
+//! global transform is propagated again. Propagation follows the parent/child links, so most reads are random access.
+
+use super::{SplitMix64, StateHash};
+use crate::world::{World, WorldCommon};
+use crate::entity::{Entity, EntityStorage};
+use crate::transform::{Transform, LocalTransform, GlobalTransform, TransformPropagation};
+use crate::system::{SystemScheduler, Scheduler};
+use std::f32::consts::PI;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug)]
+pub struct Config{
+    pub roots: usize,
+    /// Levels under each root
+    pub depth: usize,
+    pub branching: usize,
+    pub frames: usize,
+    /// Nodes rescaled and nodes reparented each frame
+    pub mutations: usize,
+    pub seed: u64,
+    pub threads: usize,
+}
+
+impl Default for Config{
+    fn default() -> Self{
+        Config{
+            roots: 8,
+            depth: 5,
+            branching: 4,
+            frames: 100,
+            mutations: 64,
+            seed: 1,
+            threads: 4,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Report{
+    /// Hash of every global transform after the last frame
+    pub hash: u64,
+    pub nodes: usize,
+    pub mutate_times: Vec<Duration>,
+    pub propagate_times: Vec<Duration>,
+}
+
+fn random_local(rng: &mut SplitMix64) -> LocalTransform{
+    LocalTransform(Transform::from_rotation_z(rng.range(-PI, PI))
+        .with_translation([rng.range(-1.0, 1.0), rng.range(-1.0, 1.0), rng.range(-1.0, 1.0)])
+        .with_scale(rng.range(0.9, 1.1)))
+}
+
+/// Builds `roots` trees where every node above the last level has `branching` children
+pub fn setup(config: &Config) -> (World, Vec<Entity>){
+    let mut world = World::new();
+    world.insert(EntityStorage::new());
+    world.register_hierarchy();
+    world.register_comp::<LocalTransform>();
+    world.register_comp::<GlobalTransform>();
+
+    let mut rng = SplitMix64::new(config.seed);
+    let mut nodes = Vec::new();
+    let spawn = |world: &World, rng: &mut SplitMix64|{
+        let entity = *world.get_mut::<EntityStorage>().create_entity();
+        world.get_comp_mut::<LocalTransform>().set(&entity.index(), random_local(rng));
+        entity
+    };
+
+    for _ in 0..config.roots{
+        let mut level = vec![spawn(&world, &mut rng)];
+        nodes.extend(level.iter().copied());
+        for _ in 0..config.depth{
+            let mut next = Vec::with_capacity(level.len() * config.branching);
+            for parent in level.iter(){
+                for _ in 0..config.branching{
+                    let child = spawn(&world, &mut rng);
+                    world.set_parent(&child, parent);
+                    next.push(child);
+                }
+            }
+            nodes.extend(next.iter().copied());
+            level = next;
+        }
+    }
+    (world, nodes)
+}
+
+fn is_ancestor(world: &World, ancestor: &Entity, entity: &Entity) -> bool{
+    let mut next = Some(*entity);
+    while let Some(node) = next{
+        if node == *ancestor{
+            return true;
+        }
+        next = world.parent(&node);
+    }
+    false
+}
+
+/// Rescales `mutations` random nodes and moves `mutations` random nodes under a random new parent.
+/// One in eight moves makes the node a root instead, moves that would make a cycle are skipped.
+pub fn mutate(world: &World, nodes: &[Entity], rng: &mut SplitMix64, mutations: usize){
+    {
+        let mut locals = world.get_comp_mut::<LocalTransform>();
+        for _ in 0..mutations{
+            let index = nodes[rng.below(nodes.len())].index();
+            let local = locals.get_mut(&index).unwrap();
+            local.0.scale = rng.range(0.9, 1.1);
+        }
+    }
+
+    for _ in 0..mutations{
+        let node = nodes[rng.below(nodes.len())];
+        if rng.below(8) == 0{
+            world.remove_parent(&node);
+            continue;
+        }
+        let parent = nodes[rng.below(nodes.len())];
+        if !is_ancestor(world, &node, &parent){
+            world.set_parent(&node, &parent);
+        }
+    }
+}
+
+pub fn hash(world: &World) -> u64{
+    let mut hash = StateHash::default();
+    let globals = world.get_comp::<GlobalTransform>();
+    for entity in world.get::<EntityStorage>().live_entities(){
+        let global = globals.get(&entity.index()).unwrap().0;
+        hash.write_u64(entity.index() as u64);
+        for value in global.translation.iter().chain(global.rotation.iter()).chain(std::iter::once(&global.scale)){
+            hash.write_f32(*value);
+        }
+    }
+    hash.finish()
+}
+
+pub fn run(config: &Config) -> Report{
+    let (world, nodes) = setup(config);
+    let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(config.threads.max(1)).build().unwrap());
+    let mut scheduler = SystemScheduler::new(pool);
+    scheduler.add(TransformPropagation, "propagate", vec![]);
+
+    // Mutations use their own stream so the forest doesn't change shape with the mutation count
+    let mut rng = SplitMix64::new(config.seed ^ 0x5EED);
+    let mut mutate_times = Vec::with_capacity(config.frames);
+    let mut propagate_times = Vec::with_capacity(config.frames);
+    for _ in 0..config.frames{
+        let start = Instant::now();
+        mutate(&world, &nodes, &mut rng, config.mutations);
+        mutate_times.push(start.elapsed());
+
+        let start = Instant::now();
+        scheduler.run(&world);
+        propagate_times.push(start.elapsed());
+    }
+
+    Report{
+        hash: hash(&world),
+        nodes: nodes.len(),
+        mutate_times,
+        propagate_times,
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn config(threads: usize) -> Config{
+        Config{
+            roots: 3,
+            depth: 3,
+            branching: 3,
+            frames: 20,
+            mutations: 10,
+            seed: 5,
+            threads,
+        }
+    }
+
+    #[test]
+    fn forest_shape(){
+        let (world, nodes) = setup(&config(1));
+        assert_eq!(nodes.len(), 3 * (1 + 3 + 9 + 27));
+        assert_eq!(world.descendants(&nodes[0]).len(), 39);
+        assert_eq!(world.children(&nodes[0]).len(), 3);
+    }
+
+    #[test]
+    fn propagation_matches_parent_chain(){
+        let config = config(4);
+        let (world, nodes) = setup(&config);
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap());
+        let mut scheduler = SystemScheduler::new(pool);
+        scheduler.add(TransformPropagation, "propagate", vec![]);
+
+        let mut rng = SplitMix64::new(9);
+        for _ in 0..config.frames{
+            mutate(&world, &nodes, &mut rng, config.mutations);
+            scheduler.run(&world);
+        }
+
+        let locals = world.get_comp::<LocalTransform>();
+        let globals = world.get_comp::<GlobalTransform>();
+        for node in nodes.iter(){
+            let mut expected = locals.get(&node.index()).unwrap().0;
+            let mut next = world.parent(node);
+            while let Some(parent) = next{
+                expected = locals.get(&parent.index()).unwrap().0.mul_transform(&expected);
+                next = world.parent(&parent);
+            }
+            let global = globals.get(&node.index()).unwrap().0;
+            assert!(global.translation.iter().zip(expected.translation.iter()).all(|(a, b)| (a - b).abs() < 1e-3));
+            assert!((global.scale - expected.scale).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn deterministic(){
+        let report = run(&config(1));
+        assert_eq!(report.mutate_times.len(), 20);
+        assert_eq!(report.propagate_times.len(), 20);
+        assert_eq!(report.hash, run(&config(4)).hash);
+        assert_ne!(report.hash, run(&Config{seed: 6, ..config(1)}).hash);
+    }
+}
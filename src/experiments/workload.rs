@@ -0,0 +1,621 @@
+//! Synthetic workloads described by a TOML or JSON config, for trying out a game's data shape on a backend.
+//!
+//! ```toml
+//! entities = 10000
+//! frames = 100
+//! spawn_rate = 20
+//! despawn_rate = 20
+//!
+//! [[components]]
+//! name = "position"
+//! size = 12
+//!
+//! [[components]]
+//! name = "burning"
+//! size = 0
+//! fraction = 0.05
+//! add_rate = 0.01
+//! remove_rate = 0.01
+//!
+//! [[systems]]
+//! name = "fire"
+//! reads = ["burning"]
+//! writes = ["position"]
+//! ```
+//!
+//! Components are plain bytes, sizes get rounded up to 0, 4, 8, 16, 32, 64, 128 or 256.
+//! Each frame entities are despawned and spawned, components are added and removed, then the systems run.
+//! A system reads every byte of the components it reads and rewrites every byte of the ones it writes,
+//! for every entity that has all of them.
+
+use super::{SplitMix64, StateHash};
+use crate::world::{World, WorldCommon};
+use crate::entity::{Entity, EntityStorage};
+use crate::system::{ReadComp, WriteComp, SystemData, SystemRunner, SystemScheduler, Scheduler, AccessType, DepVec};
+use serde::Deserialize;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Most components a config can declare
+pub const MAX_COMPONENTS: usize = 16;
+/// Biggest component size in bytes
+pub const MAX_SIZE: usize = 256;
+
+const SIZES: [usize; 8] = [0, 4, 8, 16, 32, 64, 128, 256];
+
+fn default_frames() -> usize{
+    100
+}
+
+fn default_threads() -> usize{
+    4
+}
+
+fn default_fraction() -> f32{
+    1.0
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config{
+    /// Entities spawned before the first frame
+    pub entities: usize,
+    #[serde(default = "default_frames")]
+    pub frames: usize,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default = "default_threads")]
+    pub threads: usize,
+    /// Entities spawned each frame
+    #[serde(default)]
+    pub spawn_rate: usize,
+    /// Random entities despawned each frame
+    #[serde(default)]
+    pub despawn_rate: usize,
+    pub components: Vec<ComponentConfig>,
+    #[serde(default)]
+    pub systems: Vec<SystemConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentConfig{
+    pub name: String,
+    /// Bytes
+    pub size: usize,
+    /// Chance a spawned entity has this component
+    #[serde(default = "default_fraction")]
+    pub fraction: f32,
+    /// Fraction of the live entities picked each frame to get this component if they don't have it
+    #[serde(default)]
+    pub add_rate: f32,
+    /// Fraction of the live entities picked each frame to lose this component if they have it
+    #[serde(default)]
+    pub remove_rate: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SystemConfig{
+    pub name: String,
+    #[serde(default)]
+    pub reads: Vec<String>,
+    #[serde(default)]
+    pub writes: Vec<String>,
+    /// Systems this one runs after
+    #[serde(default)]
+    pub after: Vec<String>,
+}
+
+impl Config{
+    pub fn from_toml(data: &str) -> Result<Self, WorkloadError>{
+        Ok(toml::from_str(data)?)
+    }
+
+    pub fn from_json(data: &str) -> Result<Self, WorkloadError>{
+        Ok(serde_json::from_str(data)?)
+    }
+}
+
+#[derive(Debug)]
+pub enum WorkloadError{
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    TooManyComponents(usize),
+    DuplicateComponent(String),
+    /// A component is bigger than `MAX_SIZE`
+    ComponentTooLarge(String, usize),
+    /// A fraction or rate of the component isn't between 0 and 1
+    InvalidRate(String),
+    /// A system uses a component that isn't declared
+    UnknownComponent(String),
+    /// A system runs after a system that isn't declared
+    UnknownSystem(String),
+}
+
+impl fmt::Display for WorkloadError{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        match self{
+            WorkloadError::Toml(e) => write!(f, "toml error: {}", e),
+            WorkloadError::Json(e) => write!(f, "json error: {}", e),
+            WorkloadError::TooManyComponents(count) => write!(f, "{} components declared, at most {} are supported", count, MAX_COMPONENTS),
+            WorkloadError::DuplicateComponent(name) => write!(f, "component {} is declared twice", name),
+            WorkloadError::ComponentTooLarge(name, size) => write!(f, "component {} is {} bytes, at most {} are supported", name, size, MAX_SIZE),
+            WorkloadError::InvalidRate(name) => write!(f, "component {} has a fraction or rate outside of 0 to 1", name),
+            WorkloadError::UnknownComponent(name) => write!(f, "unknown component {}", name),
+            WorkloadError::UnknownSystem(name) => write!(f, "unknown system {}", name),
+        }
+    }
+}
+
+impl std::error::Error for WorkloadError{}
+
+impl From<toml::de::Error> for WorkloadError{
+    fn from(e: toml::de::Error) -> Self{
+        WorkloadError::Toml(e)
+    }
+}
+
+impl From<serde_json::Error> for WorkloadError{
+    fn from(e: serde_json::Error) -> Self{
+        WorkloadError::Json(e)
+    }
+}
+
+/// A component that's only bytes, `SLOT` keeps components of the same size apart
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Blob<const SLOT: usize, const SIZE: usize>(pub [u8; SIZE]);
+
+trait Column{
+    fn has(&self, index: usize) -> bool;
+
+    /// Sum of every byte of the component, 0 if the entity doesn't have it
+    fn sum(&self, index: usize) -> u32;
+}
+
+trait ColumnMut{
+    fn has(&self, index: usize) -> bool;
+
+    /// Rewrites every byte of the component from `value`
+    fn mix(&mut self, index: usize, value: u32);
+}
+
+struct Reader<'w, const SLOT: usize, const SIZE: usize>(ReadComp<'w, Blob<SLOT, SIZE>>);
+
+impl<'w, const SLOT: usize, const SIZE: usize> Column for Reader<'w, SLOT, SIZE>{
+    fn has(&self, index: usize) -> bool{
+        self.0.get(index).is_some()
+    }
+
+    fn sum(&self, index: usize) -> u32{
+        self.0.get(index).map_or(0, |blob| blob.0.iter().map(|byte| u32::from(*byte)).sum())
+    }
+}
+
+struct Writer<'w, const SLOT: usize, const SIZE: usize>(WriteComp<'w, Blob<SLOT, SIZE>>);
+
+impl<'w, const SLOT: usize, const SIZE: usize> ColumnMut for Writer<'w, SLOT, SIZE>{
+    fn has(&self, index: usize) -> bool{
+        self.0.get(&index).is_some()
+    }
+
+    fn mix(&mut self, index: usize, value: u32){
+        // WriteComp::get_mut borrows for the whole guard lifetime, so the blob is copied out and set back
+        if let Some(mut blob) = self.0.get(&index).copied(){
+            for (n, byte) in blob.0.iter_mut().enumerate(){
+                *byte = byte.wrapping_add((value >> (n % 4 * 8)) as u8) ^ n as u8;
+            }
+            self.0.set(index, blob);
+        }
+    }
+}
+
+// Everything the workload does with a component, picked once from its slot and size
+#[derive(Clone, Copy)]
+struct Ops{
+    register: fn(&mut World),
+    deps: fn(&World, AccessType) -> DepVec,
+    has: fn(&World, usize) -> bool,
+    insert: fn(&World, usize, u8),
+    remove: fn(&World, usize),
+    read: for<'w> fn(&'w World) -> Box<dyn Column + 'w>,
+    write: for<'w> fn(&'w World) -> Box<dyn ColumnMut + 'w>,
+    hash: fn(&World, &mut StateHash),
+}
+
+fn register<const SLOT: usize, const SIZE: usize>(world: &mut World){
+    world.register_comp::<Blob<SLOT, SIZE>>();
+}
+
+fn deps<const SLOT: usize, const SIZE: usize>(world: &World, access: AccessType) -> DepVec{
+    world.get_dep_vec_comp::<Blob<SLOT, SIZE>>(access)
+}
+
+fn has<const SLOT: usize, const SIZE: usize>(world: &World, index: usize) -> bool{
+    world.get_comp::<Blob<SLOT, SIZE>>().get(&index).is_some()
+}
+
+fn insert<const SLOT: usize, const SIZE: usize>(world: &World, index: usize, value: u8){
+    world.get_comp_mut::<Blob<SLOT, SIZE>>().set(&index, Blob([value; SIZE]));
+}
+
+fn remove<const SLOT: usize, const SIZE: usize>(world: &World, index: usize){
+    world.get_comp_mut::<Blob<SLOT, SIZE>>().delete(&index);
+}
+
+fn reader<const SLOT: usize, const SIZE: usize>(world: &World) -> Box<dyn Column + '_>{
+    Box::new(Reader(ReadComp::<Blob<SLOT, SIZE>>::get_data(world)))
+}
+
+fn writer<const SLOT: usize, const SIZE: usize>(world: &World) -> Box<dyn ColumnMut + '_>{
+    Box::new(Writer(WriteComp::<Blob<SLOT, SIZE>>::get_data(world)))
+}
+
+fn hash<const SLOT: usize, const SIZE: usize>(world: &World, hash: &mut StateHash){
+    for (index, (valid, blob)) in world.get_comp::<Blob<SLOT, SIZE>>().iter().enumerate(){
+        if let (true, Some(blob)) = (valid, blob){
+            hash.write_u64(index as u64);
+            for chunk in blob.0.chunks(8){
+                let mut bytes = [0; 8];
+                bytes[..chunk.len()].copy_from_slice(chunk);
+                hash.write_u64(u64::from_le_bytes(bytes));
+            }
+        }
+    }
+}
+
+fn ops<const SLOT: usize, const SIZE: usize>() -> Ops{
+    Ops{
+        register: register::<SLOT, SIZE>,
+        deps: deps::<SLOT, SIZE>,
+        has: has::<SLOT, SIZE>,
+        insert: insert::<SLOT, SIZE>,
+        remove: remove::<SLOT, SIZE>,
+        read: reader::<SLOT, SIZE>,
+        write: writer::<SLOT, SIZE>,
+        hash: hash::<SLOT, SIZE>,
+    }
+}
+
+fn sized<const SLOT: usize>(size: usize) -> Ops{
+    match SIZES.iter().find(|class| **class >= size){
+        Some(0) => ops::<SLOT, 0>(),
+        Some(4) => ops::<SLOT, 4>(),
+        Some(8) => ops::<SLOT, 8>(),
+        Some(16) => ops::<SLOT, 16>(),
+        Some(32) => ops::<SLOT, 32>(),
+        Some(64) => ops::<SLOT, 64>(),
+        Some(128) => ops::<SLOT, 128>(),
+        _ => ops::<SLOT, 256>(),
+    }
+}
+
+fn ops_for(slot: usize, size: usize) -> Ops{
+    match slot{
+        0 => sized::<0>(size),
+        1 => sized::<1>(size),
+        2 => sized::<2>(size),
+        3 => sized::<3>(size),
+        4 => sized::<4>(size),
+        5 => sized::<5>(size),
+        6 => sized::<6>(size),
+        7 => sized::<7>(size),
+        8 => sized::<8>(size),
+        9 => sized::<9>(size),
+        10 => sized::<10>(size),
+        11 => sized::<11>(size),
+        12 => sized::<12>(size),
+        13 => sized::<13>(size),
+        14 => sized::<14>(size),
+        15 => sized::<15>(size),
+        _ => panic!("Component slot {} is past MAX_COMPONENTS", slot),
+    }
+}
+
+/// A system from the config, runs over every entity that has all of its components
+struct SyntheticSystem{
+    reads: Vec<Ops>,
+    writes: Vec<Ops>,
+}
+
+impl SyntheticSystem{
+    fn run(&self, world: &World){
+        let entities = world.get::<EntityStorage>();
+        let reads: Vec<Box<dyn Column>> = self.reads.iter().map(|ops| (ops.read)(world)).collect();
+        let mut writes: Vec<Box<dyn ColumnMut>> = self.writes.iter().map(|ops| (ops.write)(world)).collect();
+        for entity in entities.live_entities(){
+            let index = entity.index();
+            if !reads.iter().all(|column| column.has(index)) || !writes.iter().all(|column| column.has(index)){
+                continue;
+            }
+            let value = reads.iter().fold(1u32, |sum, column| sum.wrapping_add(column.sum(index)));
+            for column in writes.iter_mut(){
+                column.mix(index, value);
+            }
+        }
+    }
+}
+
+impl<'d, 'w: 'd> SystemRunner<'d, 'w, World> for SyntheticSystem{
+    fn get_and_run(&self, world: &'w World){
+        self.run(world);
+    }
+
+    fn get_and_run_timed(&self, world: &'w World) -> (Instant, Instant){
+        let acquired = Instant::now();
+        self.run(world);
+        (acquired, Instant::now())
+    }
+
+    fn get_system_dependencies(&self, world: &World) -> DepVec{
+        let mut deps = world.get_dep_vec_res::<EntityStorage>(AccessType::Read);
+        for ops in self.reads.iter(){
+            deps = deps.or(&(ops.deps)(world, AccessType::Read));
+        }
+        for ops in self.writes.iter(){
+            deps = deps.or(&(ops.deps)(world, AccessType::Write));
+        }
+        deps
+    }
+
+    fn setup(&self, _world: &mut World){}
+}
+
+#[derive(Clone, Debug)]
+pub struct Report{
+    /// Hash of every component of every entity after the last frame
+    pub hash: u64,
+    pub live: usize,
+    pub churn_times: Vec<Duration>,
+    pub system_times: Vec<Duration>,
+}
+
+/// A checked config along with the state of its spawning and churn
+pub struct Workload{
+    config: Config,
+    components: Vec<Ops>,
+    live: Vec<Entity>,
+    rng: SplitMix64,
+}
+
+impl Workload{
+    pub fn new(config: Config) -> Result<Self, WorkloadError>{
+        if config.components.len() > MAX_COMPONENTS{
+            return Err(WorkloadError::TooManyComponents(config.components.len()));
+        }
+        for (n, component) in config.components.iter().enumerate(){
+            if config.components[..n].iter().any(|other| other.name == component.name){
+                return Err(WorkloadError::DuplicateComponent(component.name.clone()));
+            }
+            if component.size > MAX_SIZE{
+                return Err(WorkloadError::ComponentTooLarge(component.name.clone(), component.size));
+            }
+            if [component.fraction, component.add_rate, component.remove_rate].iter().any(|rate| !(0.0..=1.0).contains(rate)){
+                return Err(WorkloadError::InvalidRate(component.name.clone()));
+            }
+        }
+        for system in config.systems.iter(){
+            if let Some(name) = system.reads.iter().chain(system.writes.iter()).find(|name| !config.components.iter().any(|c| c.name == **name)){
+                return Err(WorkloadError::UnknownComponent(name.clone()));
+            }
+            if let Some(name) = system.after.iter().find(|name| !config.systems.iter().any(|s| s.name == **name)){
+                return Err(WorkloadError::UnknownSystem(name.clone()));
+            }
+        }
+
+        let components = config.components.iter().enumerate().map(|(slot, component)| ops_for(slot, component.size)).collect();
+        Ok(Workload{
+            rng: SplitMix64::new(config.seed),
+            config,
+            components,
+            live: Vec::new(),
+        })
+    }
+
+    pub fn config(&self) -> &Config{
+        &self.config
+    }
+
+    /// Entities alive right now
+    pub fn live(&self) -> usize{
+        self.live.len()
+    }
+
+    /// Builds the world with the starting entities
+    pub fn setup(&mut self) -> World{
+        let mut world = World::new();
+        world.insert(EntityStorage::new());
+        for ops in self.components.iter(){
+            (ops.register)(&mut world);
+        }
+        for _ in 0..self.config.entities{
+            self.spawn(&world);
+        }
+        world
+    }
+
+    fn spawn(&mut self, world: &World){
+        let entity = *world.get_mut::<EntityStorage>().create_entity();
+        for (config, ops) in self.config.components.iter().zip(self.components.iter()){
+            if self.rng.next_f32() < config.fraction{
+                (ops.insert)(world, entity.index(), self.rng.next_u64() as u8);
+            }
+        }
+        self.live.push(entity);
+    }
+
+    /// Adds a system per system in the config, a component that's both read and written counts as written
+    pub fn add_systems<'d, 'w: 'd>(&self, scheduler: &mut SystemScheduler<'d, 'w>){
+        let slot = |name: &String| self.config.components.iter().position(|c| c.name == *name).unwrap();
+        for system in self.config.systems.iter(){
+            let mut writes: Vec<usize> = system.writes.iter().map(slot).collect();
+            writes.sort_unstable();
+            writes.dedup();
+            let mut reads: Vec<usize> = system.reads.iter().map(slot).filter(|n| !writes.contains(n)).collect();
+            reads.sort_unstable();
+            reads.dedup();
+
+            let runner = SyntheticSystem{
+                reads: reads.iter().map(|n| self.components[*n]).collect(),
+                writes: writes.iter().map(|n| self.components[*n]).collect(),
+            };
+            scheduler.add_runner(Box::new(runner), &system.name, system.after.iter().map(|s| s.as_str()).collect());
+        }
+    }
+
+    /// One frame of structural changes: despawns, spawns, then component removes and adds
+    pub fn churn(&mut self, world: &World){
+        for _ in 0..self.config.despawn_rate.min(self.live.len()){
+            let entity = self.live.swap_remove(self.rng.below(self.live.len()));
+            world.despawn(&entity);
+        }
+        for _ in 0..self.config.spawn_rate{
+            self.spawn(world);
+        }
+
+        let (live, rng) = (&self.live, &mut self.rng);
+        if live.is_empty(){
+            return;
+        }
+        for (config, ops) in self.config.components.iter().zip(self.components.iter()){
+            for _ in 0..(config.remove_rate * live.len() as f32).round() as usize{
+                (ops.remove)(world, live[rng.below(live.len())].index());
+            }
+            for _ in 0..(config.add_rate * live.len() as f32).round() as usize{
+                let index = live[rng.below(live.len())].index();
+                if !(ops.has)(world, index){
+                    (ops.insert)(world, index, rng.next_u64() as u8);
+                }
+            }
+        }
+    }
+
+    pub fn hash(&self, world: &World) -> u64{
+        let mut hash = StateHash::default();
+        for ops in self.components.iter(){
+            (ops.hash)(world, &mut hash);
+        }
+        hash.finish()
+    }
+
+    pub fn run(&mut self) -> Report{
+        let world = self.setup();
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(self.config.threads.max(1)).build().unwrap());
+        let mut scheduler = SystemScheduler::new(pool);
+        self.add_systems(&mut scheduler);
+
+        let mut churn_times = Vec::with_capacity(self.config.frames);
+        let mut system_times = Vec::with_capacity(self.config.frames);
+        for _ in 0..self.config.frames{
+            let start = Instant::now();
+            self.churn(&world);
+            churn_times.push(start.elapsed());
+
+            let start = Instant::now();
+            scheduler.run(&world);
+            system_times.push(start.elapsed());
+        }
+
+        Report{
+            hash: self.hash(&world),
+            live: self.live(),
+            churn_times,
+            system_times,
+        }
+    }
+}
+
+pub fn run(config: Config) -> Result<Report, WorkloadError>{
+    Ok(Workload::new(config)?.run())
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    const CONFIG: &str = r#"
+        entities = 200
+        frames = 10
+        seed = 3
+        threads = 2
+        spawn_rate = 5
+        despawn_rate = 3
+
+        [[components]]
+        name = "position"
+        size = 12
+
+        [[components]]
+        name = "velocity"
+        size = 12
+        fraction = 0.5
+
+        [[components]]
+        name = "frozen"
+        size = 0
+        fraction = 0.1
+        add_rate = 0.05
+        remove_rate = 0.05
+
+        [[systems]]
+        name = "move"
+        reads = ["velocity"]
+        writes = ["position"]
+
+        [[systems]]
+        name = "freeze"
+        reads = ["frozen"]
+        writes = ["velocity"]
+        after = ["move"]
+    "#;
+
+    #[test]
+    fn runs_config(){
+        let config = Config::from_toml(CONFIG).unwrap();
+        assert_eq!(config.components[1].fraction, 0.5);
+        let report = run(config.clone()).unwrap();
+        assert_eq!(report.live, 200 + 10 * (5 - 3));
+        assert_eq!(report.system_times.len(), 10);
+        assert_eq!(report.hash, run(config.clone()).unwrap().hash);
+        assert_ne!(report.hash, run(Config{seed: 4, ..config}).unwrap().hash);
+
+        let mut workload = Workload::new(Config::from_toml(CONFIG).unwrap()).unwrap();
+        let world = workload.setup();
+        let positions = world.get_comp::<Blob<0, 16>>();
+        assert_eq!(positions.iter().filter(|(valid, _)| *valid).count(), 200);
+    }
+
+    #[test]
+    fn systems_touch_matching_entities(){
+        let json = r#"{
+            "entities": 2,
+            "frames": 1,
+            "components": [{"name": "a", "size": 4}, {"name": "b", "size": 4, "fraction": 0.0}],
+            "systems": [{"name": "s", "reads": ["b"], "writes": ["a"]}]
+        }"#;
+        let mut workload = Workload::new(Config::from_json(json).unwrap()).unwrap();
+        let world = workload.setup();
+        world.get_comp_mut::<Blob<1, 4>>().set(&0, Blob([1; 4]));
+        let before = [*world.get_comp::<Blob<0, 4>>().get(&0).unwrap(), *world.get_comp::<Blob<0, 4>>().get(&1).unwrap()];
+        {
+            let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap());
+            let mut scheduler = SystemScheduler::new(pool);
+            workload.add_systems(&mut scheduler);
+            scheduler.run(&world);
+        }
+        // Only the entity with both components was written, with 1 + the 4 bytes of b
+        assert_eq!(world.get_comp::<Blob<0, 4>>().get(&0).unwrap().0[0], before[0].0[0].wrapping_add(5));
+        assert_eq!(*world.get_comp::<Blob<0, 4>>().get(&1).unwrap(), before[1]);
+    }
+
+    #[test]
+    fn rejects_bad_configs(){
+        let parse = |extra: &str| Workload::new(Config::from_toml(&format!("entities = 1\n{}", extra)).unwrap()).err();
+        assert!(matches!(parse("[[components]]\nname = \"a\"\nsize = 300"), Some(WorkloadError::ComponentTooLarge(_, 300))));
+        assert!(matches!(parse("[[components]]\nname = \"a\"\nsize = 4\nfraction = 2.0"), Some(WorkloadError::InvalidRate(_))));
+        assert!(matches!(parse("components = []\n[[systems]]\nname = \"s\"\nreads = [\"x\"]"), Some(WorkloadError::UnknownComponent(_))));
+        assert!(matches!(parse("components = []\n[[systems]]\nname = \"s\"\nafter = [\"t\"]"), Some(WorkloadError::UnknownSystem(_))));
+        assert!(matches!(Config::from_toml("entities = 1\ncomponents = []\nbogus = 1"), Err(WorkloadError::Toml(_))));
+    }
+}
@@ -1,12 +1,31 @@
+// Only for the crate name, the lint is checked at the crate root so this is the narrowest place it can go
+#![allow(non_snake_case)]
+
 pub mod world{
     pub use SmolCommon::WorldCommon;
-    pub use SmolHBSECS::world::World;
+    pub use SmolHBSECS::world::{World, FromWorld};
+    pub use SmolHBSECS::registry::{TypeRegistry, ComponentRegistration, ResourceRegistration};
+    pub use SmolHBSECS::snapshot::{Snapshot, SnapshotRing};
+    pub use SmolHBSECS::diff::{WorldDiff, ComponentChange, Change};
+    pub use SmolHBSECS::stats::{CompositionStats, Archetype, StorageStats, StorageChurn};
+    pub use SmolHBSECS::reflect::{ComponentInfo, ResourceInfo, StorageKind, MemoryUsage};
+    pub use SmolHBSECS::inspect::{EntityInspection, InspectedComponent};
+    #[cfg(feature = "serde")]
+    pub use SmolHBSECS::serialize::SerializeError;
+    #[cfg(feature = "serde")]
+    pub use SmolHBSECS::diff::Patch;
+    #[cfg(feature = "serde")]
+    pub use SmolHBSECS::prefab::{Prefab, Prefabs};
+    #[cfg(feature = "serde")]
+    pub use SmolHBSECS::scene::{SceneId, SceneMember, SceneEvent, SceneManager};
 }
 
 pub mod entity{
     pub use SmolCommon::entity::EntityCommon;
     pub use SmolHBSECS::{Entity, EntityStorage};
-
+    pub use SmolHBSECS::entity_map::{EntityMap, MapEntities, RemapEvents};
+    pub use SmolHBSECS::reorder::morton_code;
+    pub use SmolHBSECS::hierarchy::{Parent, Children};
 }
 
 pub mod component{
@@ -15,13 +34,30 @@ pub mod component{
 }
 
 pub mod system{
-    pub use SmolCommon::system::{ReadComp, WriteComp, Read, Write, System, SystemData, Scheduler};
+    pub use SmolCommon::system::{ReadComp, WriteComp, Read, Write, ReadExpect, WriteExpect, ReadDefault, WriteDefault, System, SystemData, Scheduler, SetupHandler, NoSetup, DefaultProvider, PanicHandler, IntoSystem, FunctionSystem, SystemRunner};
+    pub use SmolCommon::{AccessType, DepVec};
     pub use SmolCommon::join::Joinable;
-    pub use SmolHBSECS::system::SystemScheduler;
+    pub use SmolHBSECS::system::{SystemScheduler, SystemSet};
+    pub use SmolHBSECS::stage::{Stages, PRE_UPDATE, UPDATE, POST_UPDATE};
+    pub use SmolHBSECS::command::Commands;
+}
+
+pub mod transform{
+    pub use SmolHBSECS::transform::{Transform, LocalTransform, GlobalTransform, TransformPropagation};
+}
+
+pub mod time{
+    pub use SmolCommon::time::{Time, FixedTime, FixedLoop};
+}
+
+pub mod profiler{
+    pub use SmolCommon::profiler::{Profiler, SystemSpan, SystemStats};
 }
 
 pub use rayon;
 
+pub mod experiments;
+
 #[cfg(test)]
 mod tests{
     use crate::world::*;
@@ -66,6 +102,7 @@ mod tests{
 
     struct CounterCheck;
 
+    #[allow(unused_variables)]
     impl<'d, 'w: 'd> System<'d, 'w, World> for CounterCheck{
         type SystemData = (
             ReadComp<'d, usize>,
@@ -82,6 +119,7 @@ mod tests{
 
     struct SubCheck;
 
+    #[allow(unused_parens)]
     impl<'d, 'w: 'd> System<'d, 'w, World> for SubCheck{
         type SystemData = (
             ReadComp<'d, isize>,
@@ -96,6 +134,7 @@ mod tests{
     }
 
     #[test]
+    #[allow(clippy::unnecessary_cast)]
     fn run_ten_times(){
         let mut world = World::new();
 
@@ -123,4 +162,60 @@ mod tests{
             assert_eq!(*sub_reader, 0);
         }
     }
+
+    struct FixedSteps(u64);
+    struct UpdateFrames(u64);
+
+    struct CountFixed;
+
+    impl<'d, 'w: 'd> System<'d, 'w, World> for CountFixed{
+        type SystemData = Write<'d, FixedSteps>;
+
+        fn run(&self, mut steps: Self::SystemData){
+            steps.0 += 1;
+        }
+    }
+
+    struct CountUpdate;
+
+    impl<'d, 'w: 'd> System<'d, 'w, World> for CountUpdate{
+        type SystemData = Write<'d, UpdateFrames>;
+
+        fn run(&self, mut frames: Self::SystemData){
+            frames.0 += 1;
+        }
+    }
+
+    #[test]
+    fn fixed_loop_accumulates(){
+        use crate::time::*;
+        use std::time::Duration;
+
+        let mut world = World::new();
+        world.insert(FixedSteps(0));
+        world.insert(UpdateFrames(0));
+
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+        let mut update = SystemScheduler::new(pool.clone());
+        update.add(CountUpdate{}, "CountUpdate", vec![]);
+        let mut fixed = SystemScheduler::new(pool);
+        fixed.add(CountFixed{}, "CountFixed", vec![]);
+
+        let mut game_loop = FixedLoop::new(update, fixed, Duration::from_millis(10)).with_max_steps(4);
+        game_loop.setup(&mut world);
+
+        assert_eq!(game_loop.run_frame(&world, Duration::from_millis(25)), 2);
+        assert_eq!(game_loop.run_frame(&world, Duration::from_millis(25)), 3);
+        assert!((world.get::<FixedTime>().alpha() - 0.0).abs() < 1e-9);
+
+        // Only 4 catch up steps are allowed, the rest is dropped
+        assert_eq!(game_loop.run_frame(&world, Duration::from_millis(95)), 4);
+        assert!((world.get::<FixedTime>().alpha() - 0.5).abs() < 1e-9);
+
+        assert_eq!(world.get::<FixedSteps>().0, 9);
+        assert_eq!(world.get::<FixedTime>().tick(), 9);
+        assert_eq!(world.get::<UpdateFrames>().0, 3);
+        assert_eq!(world.get::<Time>().frame(), 3);
+        assert_eq!(world.get::<Time>().elapsed(), Duration::from_millis(145));
+    }
 }
\ No newline at end of file